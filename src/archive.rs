@@ -0,0 +1,163 @@
+use crate::error::GhError;
+use crate::prune::PrunedIssue;
+use std::path::Path;
+use std::process::Command;
+
+/// How a fetch changed an issue's file, for the commit message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    New,
+    Updated,
+    Closed,
+    Reopened,
+}
+
+impl ChangeKind {
+    fn heading(self) -> &'static str {
+        match self {
+            ChangeKind::New => "New",
+            ChangeKind::Updated => "Updated",
+            ChangeKind::Closed => "Closed",
+            ChangeKind::Reopened => "Reopened",
+        }
+    }
+
+    fn summary(self) -> &'static str {
+        match self {
+            ChangeKind::New => "new",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Closed => "closed",
+            ChangeKind::Reopened => "reopened",
+        }
+    }
+
+    /// Classifies a written issue from whether it had a file before and the state it
+    /// was in then.
+    pub fn of(had_file: bool, previous_state: Option<&str>, state: &str) -> Self {
+        match previous_state {
+            _ if !had_file => ChangeKind::New,
+            Some("open") if state == "closed" => ChangeKind::Closed,
+            Some("closed") if state == "open" => ChangeKind::Reopened,
+            _ => ChangeKind::Updated,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub number: u64,
+    pub title: String,
+    pub kind: ChangeKind,
+}
+
+/// The message of a run's commit: a subject counting the changes, then the issues
+/// under a heading per kind of change.
+pub fn commit_message(repository: &str, changes: &[Change], pruned: &[PrunedIssue]) -> String {
+    const KINDS: [ChangeKind; 4] = [
+        ChangeKind::New,
+        ChangeKind::Updated,
+        ChangeKind::Closed,
+        ChangeKind::Reopened,
+    ];
+
+    let mut counts = KINDS
+        .iter()
+        .map(|&kind| (kind, changes.iter().filter(|c| c.kind == kind).count()))
+        .filter(|(_, count)| *count > 0)
+        .map(|(kind, count)| format!("{} {}", count, kind.summary()))
+        .collect::<Vec<_>>();
+    if !pruned.is_empty() {
+        counts.push(format!("{} pruned", pruned.len()));
+    }
+
+    let mut message = format!("Update {} issues", repository);
+    if !counts.is_empty() {
+        message.push_str(&format!(": {}", counts.join(", ")));
+    }
+
+    for kind in KINDS {
+        let mut section = changes.iter().filter(|c| c.kind == kind).peekable();
+        if section.peek().is_none() {
+            continue;
+        }
+        message.push_str(&format!("\n\n{}:", kind.heading()));
+        for change in section {
+            message.push_str(&format!("\n- #{} {}", change.number, change.title));
+        }
+    }
+    if !pruned.is_empty() {
+        message.push_str("\n\nPruned:");
+        for issue in pruned {
+            message.push_str(&format!("\n- #{} {}", issue.number, issue.reason));
+        }
+    }
+    message.push('\n');
+    message
+}
+
+/// Commits everything under `dir` except `exclude` (the raw cache, when it's inside),
+/// initializing a repository there if `dir` isn't in one yet. When `dir` is inside
+/// another repository, only `dir` is committed and other staged changes are left
+/// alone. Returns the short commit hash, or `None` if nothing changed.
+pub fn commit_output(
+    dir: &Path,
+    exclude: Option<&Path>,
+    message: &str,
+) -> Result<Option<String>, GhError> {
+    if git(dir, &["rev-parse", "--show-toplevel"]).is_err() {
+        git(dir, &["init", "--quiet"])?;
+    }
+
+    let mut pathspec = vec![".".to_string()];
+    if let Some(exclude) = exclude.and_then(|exclude| exclude.strip_prefix(dir).ok()) {
+        pathspec.push(format!(":(exclude){}", exclude.display()));
+    }
+    let with_pathspec = |args: &[&str]| {
+        let mut args = args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        args.push("--".to_string());
+        args.extend(pathspec.iter().cloned());
+        args
+    };
+
+    git(dir, &to_refs(&with_pathspec(&["add", "--all"])))?;
+    let staged = Command::new("git")
+        .current_dir(dir)
+        .args(with_pathspec(&["diff", "--cached", "--quiet"]))
+        .status()
+        .map_err(|e| GhError::GitError(format!("couldn't run git: {}", e)))?;
+    if staged.success() {
+        return Ok(None);
+    }
+
+    // Scheduled runs often have no git identity configured
+    let mut commit = Vec::new();
+    if git(dir, &["config", "user.email"]).is_err() {
+        commit.extend(["-c", "user.name=ghif", "-c", "user.email=ghif@localhost"]);
+    }
+    commit.extend(["commit", "--quiet", "--message", message]);
+    git(dir, &to_refs(&with_pathspec(&commit)))?;
+
+    git(dir, &["rev-parse", "--short", "HEAD"]).map(Some)
+}
+
+fn to_refs(args: &[String]) -> Vec<&str> {
+    args.iter().map(String::as_str).collect()
+}
+
+/// Runs git in `dir`, returning its trimmed stdout.
+fn git(dir: &Path, args: &[&str]) -> Result<String, GhError> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .map_err(|e| GhError::GitError(format!("couldn't run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(GhError::GitError(format!(
+            "git {}: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,162 @@
+use crate::error::GhError;
+use crate::files::write_atomic;
+use crate::github::{CommentData, GitHubClient, IssueData};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use url::Url;
+
+/// An asset that couldn't be downloaded, so its link was left pointing at GitHub.
+#[derive(Debug)]
+pub struct AssetFailure {
+    pub url: String,
+    pub error: GhError,
+}
+
+/// Finds links to GitHub-hosted images and attachments in Markdown or HTML text.
+pub fn find_asset_links(text: &str) -> Vec<&str> {
+    let mut links = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("https://") {
+        let candidate = &rest[start..];
+        let end = candidate
+            .find(|c: char| c.is_whitespace() || "()[]<>\"'`".contains(c))
+            .unwrap_or(candidate.len());
+        let link = &candidate[..end];
+        if is_asset_url(link) && !links.contains(&link) {
+            links.push(link);
+        }
+        rest = &candidate[end..];
+    }
+
+    links
+}
+
+fn is_asset_url(link: &str) -> bool {
+    let Ok(url) = Url::parse(link) else {
+        return false;
+    };
+    let segments = url
+        .path_segments()
+        .map(|s| s.collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    match url.host_str() {
+        Some("user-images.githubusercontent.com")
+        | Some("private-user-images.githubusercontent.com") => true,
+        // github.com/user-attachments/... and github.com/<owner>/<repo>/{assets,files}/...
+        Some("github.com") => {
+            segments.first() == Some(&"user-attachments")
+                || (segments.len() > 3 && matches!(segments[2], "assets" | "files"))
+        }
+        _ => false,
+    }
+}
+
+/// Local file name for an asset: a short hash of the URL (so distinct uploads with the
+/// same name don't collide) followed by the sanitized last path segment.
+pub fn asset_file_name(link: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(link.as_bytes()));
+    let name = Url::parse(link)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut s| s.next_back().map(str::to_string))
+        })
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+            _ => '-',
+        })
+        .collect::<String>();
+
+    if name.is_empty() {
+        hash[..8].to_string()
+    } else {
+        format!("{}-{}", &hash[..8], name)
+    }
+}
+
+fn rewrite_links(text: &str, replacements: &HashMap<String, String>) -> String {
+    // Longest links first, so a link that prefixes another can't clobber it
+    let mut replacements = replacements.iter().collect::<Vec<_>>();
+    replacements.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+    replacements
+        .into_iter()
+        .fold(text.to_string(), |text, (from, to)| text.replace(from, to))
+}
+
+/// Downloads the assets referenced by an issue and its comments into
+/// `assets/<issue>/` under `output_dir`, and rewrites the links to the local copies.
+/// `link_prefix` leads from where the issue is written back to `output_dir`, e.g.
+/// `../` for an issue file in a subdirectory.
+///
+/// Assets that fail to download keep their original link and are returned with the
+/// reason. Without a client (when re-rendering from the cache), only assets downloaded
+/// by an earlier run are linked.
+pub async fn localize_assets(
+    client: Option<&GitHubClient>,
+    output_dir: &Path,
+    link_prefix: &str,
+    issue: &mut IssueData,
+    comments: &mut [CommentData],
+) -> Result<Vec<AssetFailure>, GhError> {
+    let mut links = issue
+        .body()
+        .map(find_asset_links)
+        .unwrap_or_default()
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    for comment in comments.iter() {
+        for link in find_asset_links(&comment.body) {
+            if !links.iter().any(|l| l == link) {
+                links.push(link.to_string());
+            }
+        }
+    }
+    if links.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let relative_dir = format!("assets/{}", issue.number());
+    let asset_dir = output_dir.join(&relative_dir);
+    if client.is_some() {
+        std::fs::create_dir_all(&asset_dir)?;
+    }
+
+    let mut replacements = HashMap::new();
+    let mut failures = Vec::new();
+    for link in links {
+        let file_name = asset_file_name(&link);
+        let path = asset_dir.join(&file_name);
+        if !path.exists() {
+            let Some(client) = client else {
+                continue;
+            };
+            match client.download_asset(&link).await {
+                Ok(bytes) => write_atomic(&path, bytes)?,
+                Err(error) => {
+                    failures.push(AssetFailure { url: link, error });
+                    continue;
+                }
+            }
+        }
+        replacements.insert(
+            link,
+            format!("{}{}/{}", link_prefix, relative_dir, file_name),
+        );
+    }
+
+    if let Some(body) = issue.body() {
+        let body = rewrite_links(body, &replacements);
+        issue.set_body(Some(body));
+    }
+    for comment in comments.iter_mut() {
+        comment.body = rewrite_links(&comment.body, &replacements);
+    }
+
+    Ok(failures)
+}
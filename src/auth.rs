@@ -0,0 +1,226 @@
+use crate::error::GhError;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+const GITHUB_HOST: &str = "github.com";
+
+/// Where a token was found, shown so users can tell which credential is in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    Flag,
+    Env(&'static str),
+    GhCli,
+    GitCredential,
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenSource::Flag => write!(f, "--token"),
+            TokenSource::Env(var) => write!(f, "${}", var),
+            TokenSource::GhCli => write!(f, "the gh CLI"),
+            TokenSource::GitCredential => write!(f, "git credential"),
+        }
+    }
+}
+
+/// A GitHub App, authenticating as one of its installations.
+#[derive(Debug, Clone)]
+pub struct AppCredentials {
+    pub app_id: u64,
+    /// PEM-encoded RSA private key
+    pub private_key: String,
+    /// Looked up from the repository when not given
+    pub installation_id: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    Token { token: String, source: TokenSource },
+    App(AppCredentials),
+    Anonymous,
+}
+
+/// Credential options given on the command line.
+#[derive(Debug, Default, Clone)]
+pub struct AuthOptions {
+    pub token: Option<String>,
+    pub app_id: Option<u64>,
+    /// Path to the app's private key, or the PEM contents themselves
+    pub app_key: Option<String>,
+    pub app_installation_id: Option<u64>,
+}
+
+impl Credentials {
+    /// Resolves credentials from, in order: `--token`, `GITHUB_TOKEN`/`GH_TOKEN`, a
+    /// configured GitHub App, the gh CLI, and git's credential helpers. Explicitly
+    /// configured credentials come before the ones picked up from other tools.
+    pub fn resolve(options: &AuthOptions) -> Result<Self, GhError> {
+        if let Some(token) = options.token.as_deref().and_then(non_empty) {
+            return Ok(Credentials::Token {
+                token,
+                source: TokenSource::Flag,
+            });
+        }
+
+        for var in ["GITHUB_TOKEN", "GH_TOKEN"] {
+            if let Some(token) = std::env::var(var).ok().as_deref().and_then(non_empty) {
+                return Ok(Credentials::Token {
+                    token,
+                    source: TokenSource::Env(var),
+                });
+            }
+        }
+
+        match (options.app_id, &options.app_key) {
+            (Some(app_id), Some(key)) => {
+                return Ok(Credentials::App(AppCredentials {
+                    app_id,
+                    private_key: read_private_key(key)?,
+                    installation_id: options.app_installation_id,
+                }));
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(GhError::AuthError(
+                    "--app-id and --app-key must be given together".to_string(),
+                ));
+            }
+            (None, None) => {}
+        }
+
+        if let Some(token) = gh_cli_token() {
+            return Ok(Credentials::Token {
+                token,
+                source: TokenSource::GhCli,
+            });
+        }
+        if let Some(token) = git_credential_token() {
+            return Ok(Credentials::Token {
+                token,
+                source: TokenSource::GitCredential,
+            });
+        }
+
+        Ok(Credentials::Anonymous)
+    }
+
+    /// A short description of the credential in use, e.g. `token from the gh CLI`.
+    pub fn describe(&self) -> String {
+        match self {
+            Credentials::Token { source, .. } => format!("token from {}", source),
+            Credentials::App(app) => format!("GitHub App {}", app.app_id),
+            Credentials::Anonymous => "no credentials".to_string(),
+        }
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn read_private_key(key: &str) -> Result<String, GhError> {
+    if key.trim_start().starts_with("-----BEGIN") {
+        return Ok(key.to_string());
+    }
+    std::fs::read_to_string(key).map_err(|e| {
+        GhError::AuthError(format!(
+            "Failed to read GitHub App private key {}: {}",
+            key, e
+        ))
+    })
+}
+
+/// Asks `gh auth token`, which also covers tokens kept in the system keyring,
+/// falling back to reading gh's `hosts.yml` directly.
+fn gh_cli_token() -> Option<String> {
+    Command::new("gh")
+        .args(["auth", "token", "--hostname", GITHUB_HOST])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| non_empty(&String::from_utf8_lossy(&output.stdout)))
+        .or_else(|| {
+            let content = std::fs::read_to_string(gh_config_dir()?.join("hosts.yml")).ok()?;
+            hosts_token(&content)
+        })
+}
+
+/// `$GH_CONFIG_DIR`, else `$XDG_CONFIG_HOME/gh`, else `~/.config/gh`.
+fn gh_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("GH_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(config_dir.join("gh"))
+}
+
+/// Reads the active `oauth_token` for github.com from gh's `hosts.yml`:
+///
+/// ```yaml
+/// github.com:
+///     user: octocat
+///     oauth_token: gho_...
+/// ```
+///
+/// Only direct children of the host are considered, so tokens of other accounts
+/// nested under `users:` are skipped.
+fn hosts_token(content: &str) -> Option<String> {
+    let mut in_host = false;
+    let mut child_indent = None;
+
+    for line in content.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if indent == 0 {
+            in_host = line.trim_end() == format!("{}:", GITHUB_HOST);
+            child_indent = None;
+            continue;
+        }
+        if !in_host || *child_indent.get_or_insert(indent) != indent {
+            continue;
+        }
+        if let Some(token) = line.trim().strip_prefix("oauth_token:") {
+            return non_empty(token.trim().trim_matches(&['"', '\''][..]));
+        }
+    }
+
+    None
+}
+
+/// Asks git's configured credential helpers for a github.com password, without
+/// letting git prompt for one.
+fn git_credential_token() -> Option<String> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GCM_INTERACTIVE", "never")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child
+        .stdin
+        .take()?
+        .write_all(format!("protocol=https\nhost={}\n\n", GITHUB_HOST).as_bytes())
+        .ok()?;
+
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .and_then(non_empty)
+}
@@ -0,0 +1,298 @@
+use crate::format::FormatWriter;
+use crate::github::{CommentData, IssueData};
+use serde::Serialize;
+
+/// Rough token estimate for rendered output.
+///
+/// Uses the common ~4 characters per token heuristic, which is close enough for
+/// English-heavy issue threads on BPE tokenizers and needs no model-specific vocabulary.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Comments dropped from the middle of a thread to fit the token budget.
+#[derive(Debug, Clone, Serialize)]
+pub struct Elision {
+    /// Index in the kept comment list before which the omitted comments belonged
+    pub position: usize,
+    /// Number of comments omitted
+    pub omitted: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenderedIssue {
+    pub number: u64,
+    pub content: String,
+    pub tokens: usize,
+    pub elision: Option<Elision>,
+    pub over_budget: bool,
+}
+
+impl RenderedIssue {
+    /// Renders an issue with its full comment thread.
+    pub fn full(writer: &dyn FormatWriter, issue: &IssueData, comments: &[CommentData]) -> Self {
+        let content = writer.write_issue(issue, comments, None);
+        RenderedIssue {
+            number: issue.number(),
+            tokens: estimate_tokens(&content),
+            content,
+            elision: None,
+            over_budget: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBudget {
+    pub max_tokens: usize,
+    pub keep_comments: usize,
+}
+
+impl TokenBudget {
+    /// Renders an issue, trimming the comment thread down to the first and last
+    /// `keep_comments` comments (and fewer, if needed) until it fits `max_tokens`.
+    pub fn render(
+        &self,
+        writer: &dyn FormatWriter,
+        issue: &IssueData,
+        comments: &[CommentData],
+    ) -> RenderedIssue {
+        let full = RenderedIssue::full(writer, issue, comments);
+        if full.tokens <= self.max_tokens {
+            return full;
+        }
+
+        let max_keep = self.keep_comments.min(comments.len() / 2);
+        let mut best = None;
+        for keep in (0..=max_keep).rev() {
+            let omitted = comments.len() - 2 * keep;
+            if omitted == 0 {
+                continue;
+            }
+            let kept = comments[..keep]
+                .iter()
+                .chain(&comments[comments.len() - keep..])
+                .cloned()
+                .collect::<Vec<_>>();
+            let elision = Elision {
+                position: keep,
+                omitted,
+            };
+            let content = writer.write_issue(issue, &kept, Some(&elision));
+            let tokens = estimate_tokens(&content);
+            let fits = tokens <= self.max_tokens;
+            best = Some((content, tokens, elision));
+            if fits {
+                break;
+            }
+        }
+
+        match best {
+            Some((content, tokens, elision)) => RenderedIssue {
+                number: issue.number(),
+                over_budget: tokens > self.max_tokens,
+                content,
+                tokens,
+                elision: Some(elision),
+            },
+            None => RenderedIssue {
+                over_budget: true,
+                ..full
+            },
+        }
+    }
+}
+
+/// A group of rendered issues that together fit within the token budget.
+#[derive(Debug, Serialize)]
+pub struct Chunk {
+    pub file: String,
+    pub tokens: usize,
+    pub issues: Vec<ChunkEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkEntry {
+    pub number: u64,
+    pub tokens: usize,
+    pub elision: Option<Elision>,
+    pub over_budget: bool,
+}
+
+/// Greedily packs rendered issues, in order, into chunks of at most `max_tokens`.
+///
+/// An issue that is larger than the budget on its own still gets a chunk of its own.
+pub fn pack_chunks(rendered: &[RenderedIssue], max_tokens: Option<usize>) -> Vec<Vec<usize>> {
+    let mut chunks: Vec<Vec<usize>> = Vec::new();
+    let mut current = Vec::new();
+    let mut current_tokens = 0;
+
+    for (i, issue) in rendered.iter().enumerate() {
+        let full = max_tokens.is_some_and(|max| current_tokens + issue.tokens > max);
+        if full && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        current.push(i);
+        current_tokens += issue.tokens;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Writes 10 tokens for the issue and 10 for each comment, naming the comments kept.
+    struct FixedWriter;
+
+    impl FormatWriter for FixedWriter {
+        fn write_issue(
+            &self,
+            _issue: &IssueData,
+            comments: &[CommentData],
+            _elision: Option<&Elision>,
+        ) -> String {
+            let mut content = "#".repeat(40);
+            for comment in comments {
+                content.push_str(&format!("{:<40}", comment.id));
+            }
+            content
+        }
+    }
+
+    fn issue() -> IssueData {
+        serde_json::from_value(json!({
+            "number": 1, "title": "Crash", "state": "open", "body": null,
+            "labels": [], "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z", "closed_at": null, "assignees": [],
+            "user": "octocat", "html_url": "https://github.com/owner/repo/issues/1",
+            "comments_url": null, "comment_count": 0, "state_reason": null,
+            "closed_by": null, "milestone": null, "locked": false,
+            "active_lock_reason": null, "author_association": "NONE"
+        }))
+        .unwrap()
+    }
+
+    fn comments(count: u64) -> Vec<CommentData> {
+        (1..=count)
+            .map(|id| {
+                serde_json::from_value(json!({
+                    "id": id, "html_url": "", "user": "octocat", "body": "",
+                    "created_at": "2024-01-01T00:00:00Z", "updated_at": null,
+                    "author_association": "NONE", "edited": false, "is_bot": false,
+                    "minimized": false, "minimized_reason": null
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn render(comment_count: u64, max_tokens: usize, keep_comments: usize) -> RenderedIssue {
+        let budget = TokenBudget {
+            max_tokens,
+            keep_comments,
+        };
+        budget.render(&FixedWriter, &issue(), &comments(comment_count))
+    }
+
+    fn kept(rendered: &RenderedIssue) -> Vec<u64> {
+        rendered.content.as_bytes()[40..]
+            .chunks(40)
+            .map(|id| std::str::from_utf8(id).unwrap().trim().parse().unwrap())
+            .collect()
+    }
+
+    fn elision(rendered: &RenderedIssue) -> Option<(usize, usize)> {
+        rendered.elision.as_ref().map(|e| (e.position, e.omitted))
+    }
+
+    #[test]
+    fn estimates_four_characters_a_token() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("ééééé"), 2);
+    }
+
+    #[test]
+    fn keeps_threads_that_fit() {
+        let rendered = render(4, 50, 3);
+        assert_eq!(rendered.tokens, 50);
+        assert_eq!(kept(&rendered), [1, 2, 3, 4]);
+        assert_eq!(elision(&rendered), None);
+        assert!(!rendered.over_budget);
+    }
+
+    #[test]
+    fn keeps_first_and_last_comments() {
+        let rendered = render(10, 70, 3);
+        assert_eq!(kept(&rendered), [1, 2, 3, 8, 9, 10]);
+        assert_eq!(elision(&rendered), Some((3, 4)));
+        assert!(!rendered.over_budget);
+    }
+
+    #[test]
+    fn keeps_fewer_comments_when_needed() {
+        let rendered = render(10, 35, 3);
+        assert_eq!(kept(&rendered), [1, 10]);
+        assert_eq!(elision(&rendered), Some((1, 8)));
+        assert!(!rendered.over_budget);
+    }
+
+    #[test]
+    fn keeps_at_most_half_the_thread_on_each_side() {
+        let rendered = render(3, 35, 5);
+        assert_eq!(kept(&rendered), [1, 3]);
+        assert_eq!(elision(&rendered), Some((1, 1)));
+    }
+
+    #[test]
+    fn flags_issues_still_over_budget() {
+        let rendered = render(10, 5, 3);
+        assert!(kept(&rendered).is_empty());
+        assert_eq!(elision(&rendered), Some((0, 10)));
+        assert!(rendered.over_budget);
+
+        let rendered = render(0, 5, 3);
+        assert_eq!(elision(&rendered), None);
+        assert!(rendered.over_budget);
+    }
+
+    fn sized(tokens: &[usize]) -> Vec<RenderedIssue> {
+        tokens
+            .iter()
+            .enumerate()
+            .map(|(i, &tokens)| RenderedIssue {
+                number: i as u64 + 1,
+                content: String::new(),
+                tokens,
+                elision: None,
+                over_budget: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn packs_issues_in_order_up_to_the_budget() {
+        assert_eq!(
+            pack_chunks(&sized(&[30, 30, 30, 10]), Some(60)),
+            [vec![0, 1], vec![2, 3]]
+        );
+        assert_eq!(pack_chunks(&sized(&[30, 30, 30]), None), [vec![0, 1, 2]]);
+        assert!(pack_chunks(&[], Some(60)).is_empty());
+    }
+
+    #[test]
+    fn gives_oversized_issues_a_chunk_of_their_own() {
+        assert_eq!(
+            pack_chunks(&sized(&[10, 100, 10]), Some(50)),
+            [vec![0], vec![1], vec![2]]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::error::GhError;
+use crate::files::write_atomic;
+use crate::github::{CommentData, IssueData};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the cache directory created under the output directory by default.
+pub const DEFAULT_CACHE_DIR: &str = ".ghif-cache";
+
+/// An issue and its comments exactly as fetched, before any filtering or rendering.
+#[derive(Deserialize)]
+pub struct CachedIssue {
+    pub issue: IssueData,
+    /// Empty when only issue metadata was fetched (e.g. for the csv/tsv formats)
+    #[serde(default)]
+    pub comments: Vec<CommentData>,
+    /// Whether the comments' minimized status was fetched; without it every comment
+    /// reads as not minimized
+    #[serde(default)]
+    pub minimized_status: bool,
+}
+
+#[derive(Serialize)]
+struct CachedIssueRef<'a> {
+    issue: &'a IssueData,
+    comments: &'a [CommentData],
+    minimized_status: bool,
+}
+
+/// Raw fetched data, one JSON file per issue, so output can be regenerated in any
+/// format without calling the API again.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Cache { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!("issue-{}.json", number))
+    }
+
+    pub fn store(
+        &self,
+        issue: &IssueData,
+        comments: &[CommentData],
+        minimized_status: bool,
+    ) -> Result<(), GhError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let content = serde_json::to_string_pretty(&CachedIssueRef {
+            issue,
+            comments,
+            minimized_status,
+        })
+        .map_err(|e| GhError::CacheError(e.to_string()))?;
+        write_atomic(&self.path(issue.number()), content)?;
+        Ok(())
+    }
+
+    /// Updates an issue's metadata, keeping any comments cached by an earlier fetch.
+    pub fn store_issue(&self, issue: &IssueData) -> Result<(), GhError> {
+        let (comments, minimized_status) = self
+            .load(issue.number())?
+            .map(|cached| (cached.comments, cached.minimized_status))
+            .unwrap_or_default();
+        self.store(issue, &comments, minimized_status)
+    }
+
+    pub fn load(&self, number: u64) -> Result<Option<CachedIssue>, GhError> {
+        let path = self.path(number);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        load_entry(&path).map(Some)
+    }
+
+    /// Drops an issue's entry, if it has one.
+    pub fn remove(&self, number: u64) -> Result<(), GhError> {
+        match std::fs::remove_file(self.path(number)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every cached issue, newest number first.
+    pub fn load_all(&self) -> Result<Vec<CachedIssue>, GhError> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut issues = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_entry = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("issue-") && name.ends_with(".json"));
+            if is_entry {
+                issues.push(load_entry(&path)?);
+            }
+        }
+        issues.sort_by_key(|cached| std::cmp::Reverse(cached.issue.number()));
+        Ok(issues)
+    }
+}
+
+fn load_entry(path: &Path) -> Result<CachedIssue, GhError> {
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| GhError::CacheError(format!("{}: {}", path.display(), e)))
+}
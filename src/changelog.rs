@@ -0,0 +1,209 @@
+use crate::dates::DateFormat;
+use crate::error::GhError;
+use crate::github::IssueData;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Where GitHub's automatically generated release notes are configured.
+pub const RELEASE_CONFIG_FILES: [&str; 2] = [".github/release.yml", ".github/release.yaml"];
+
+/// The `changelog` section of `.github/release.yml`, in GitHub's format: excluded
+/// labels and authors, and categories matched by label (`*` matches anything).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseConfig {
+    #[serde(default)]
+    exclude: Exclude,
+    #[serde(default)]
+    categories: Vec<Category>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Exclude {
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Category {
+    title: String,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    exclude: Exclude,
+}
+
+#[derive(Deserialize)]
+struct ReleaseFile {
+    changelog: ReleaseConfig,
+}
+
+impl Default for ReleaseConfig {
+    /// Features, bug fixes and everything else, for repositories without a config.
+    fn default() -> Self {
+        let category = |title: &str, labels: &[&str]| Category {
+            title: title.to_string(),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            exclude: Exclude::default(),
+        };
+        ReleaseConfig {
+            exclude: Exclude::default(),
+            categories: vec![
+                category("Features", &["enhancement", "feature", "feat"]),
+                category("Bug Fixes", &["bug", "fix"]),
+                category("Other Changes", &["*"]),
+            ],
+        }
+    }
+}
+
+impl ReleaseConfig {
+    pub fn load(path: &Path) -> Result<Self, GhError> {
+        let content = std::fs::read_to_string(path)?;
+        serde_yaml::from_str::<ReleaseFile>(&content)
+            .map(|file| file.changelog)
+            .map_err(|e| GhError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The release config under `repo_root`, if it has one.
+    pub fn find(repo_root: &Path) -> Option<PathBuf> {
+        RELEASE_CONFIG_FILES
+            .iter()
+            .map(|file| repo_root.join(file))
+            .find(|path| path.is_file())
+    }
+
+    /// Sorts issues into the categories, in order. Like GitHub, an issue goes into
+    /// the first category it matches; issues matching none go into "Other Changes".
+    pub fn categorize<'a>(&self, issues: &[&'a IssueData]) -> Vec<Section<'a>> {
+        let mut sections = self
+            .categories
+            .iter()
+            .map(|category| Section {
+                title: category.title.clone(),
+                issues: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let mut other = Vec::new();
+
+        for &issue in issues {
+            if self.exclude.matches(issue) {
+                continue;
+            }
+            let category = self.categories.iter().position(|category| {
+                !category.exclude.matches(issue)
+                    && category
+                        .labels
+                        .iter()
+                        .any(|label| label == "*" || issue.labels().iter().any(|l| l == label))
+            });
+            match category {
+                Some(i) => sections[i].issues.push(issue),
+                None => other.push(issue),
+            }
+        }
+
+        if !other.is_empty() {
+            sections.push(Section {
+                title: "Other Changes".to_string(),
+                issues: other,
+            });
+        }
+        sections.retain(|section| !section.issues.is_empty());
+        sections
+    }
+}
+
+impl Exclude {
+    fn matches(&self, issue: &IssueData) -> bool {
+        self.authors.iter().any(|author| author == issue.user())
+            || issue
+                .labels()
+                .iter()
+                .any(|label| self.labels.contains(label))
+    }
+}
+
+pub struct Section<'a> {
+    pub title: String,
+    pub issues: Vec<&'a IssueData>,
+}
+
+/// When an issue or pull request shipped: when the pull request was merged, or when
+/// the issue was closed as completed. `None` for anything that didn't ship (closed
+/// unmerged, or closed as not planned or as a duplicate). Issues closed before
+/// GitHub recorded reasons have none, and count as completed.
+pub fn shipped_at(issue: &IssueData) -> Option<&DateTime<Utc>> {
+    if issue.is_pull_request() {
+        issue.merged_at()
+    } else if matches!(issue.state_reason(), None | Some("completed")) {
+        issue.closed_at()
+    } else {
+        None
+    }
+}
+
+/// Renders the sections as a Markdown changelog under `title`.
+pub fn render_changelog(title: &str, sections: &[Section], dates: &DateFormat) -> String {
+    let mut out = format!("## {}\n", title);
+    if sections.is_empty() {
+        out.push_str("\nNo changes.\n");
+    }
+
+    for section in sections {
+        out.push_str(&format!("\n### {}\n\n", section.title));
+        for issue in &section.issues {
+            let shipped = shipped_at(issue)
+                .map(|date| format!(" ({})", dates.render(date)))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "- {} ([#{}]({})) by @{}{}\n",
+                issue.title(),
+                issue.number(),
+                issue.html_url(),
+                issue.user(),
+                shipped
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn closed_issue(state_reason: Option<&str>, pull_request: bool) -> IssueData {
+        serde_json::from_value(json!({
+            "number": 1, "title": "Crash", "state": "closed", "body": null,
+            "labels": [], "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z", "closed_at": "2024-01-02T00:00:00Z",
+            "assignees": [], "user": "octocat",
+            "html_url": "https://github.com/owner/repo/issues/1", "comments_url": null,
+            "comment_count": 0, "state_reason": state_reason, "closed_by": null,
+            "milestone": null, "locked": false, "active_lock_reason": null,
+            "author_association": "NONE", "pull_request": pull_request, "merged_at": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn ships_issues_closed_as_completed_or_without_reason() {
+        assert!(shipped_at(&closed_issue(Some("completed"), false)).is_some());
+        assert!(shipped_at(&closed_issue(None, false)).is_some());
+    }
+
+    #[test]
+    fn leaves_out_issues_not_planned_or_duplicate() {
+        assert!(shipped_at(&closed_issue(Some("not_planned"), false)).is_none());
+        assert!(shipped_at(&closed_issue(Some("duplicate"), false)).is_none());
+    }
+
+    #[test]
+    fn leaves_out_unmerged_pull_requests() {
+        assert!(shipped_at(&closed_issue(None, true)).is_none());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::GhError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the per-repository config file, looked up at the repository root.
+pub const REPO_CONFIG_FILE: &str = ".ghif.toml";
+
+/// Option values that can be set from a config file. Every field mirrors a CLI flag
+/// of the same name; flags given on the command line take precedence.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    pub output: Option<PathBuf>,
+    pub repository: Option<String>,
+    pub issues: Option<String>,
+    pub state: Option<String>,
+    pub batch_size: Option<usize>,
+    pub skip_existing: Option<bool>,
+    pub git_commit: Option<bool>,
+    pub with_minimized_status: Option<bool>,
+    pub format: Option<String>,
+    pub date_format: Option<String>,
+    pub timezone: Option<String>,
+    pub max_tokens: Option<usize>,
+    pub keep_comments: Option<usize>,
+    pub chunk_size: Option<usize>,
+    pub chunk_overlap: Option<usize>,
+    pub exclude_bots: Option<bool>,
+    pub exclude_minimized: Option<bool>,
+    pub download_assets: Option<bool>,
+    pub combine: Option<bool>,
+    pub cache_dir: Option<PathBuf>,
+    pub log_format: Option<String>,
+    pub filename_template: Option<String>,
+    pub prune: Option<String>,
+    pub prune_unavailable: Option<bool>,
+}
+
+impl Settings {
+    /// Fills every option set in `other` over this one.
+    fn overlay(&mut self, other: Settings) {
+        macro_rules! overlay {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field;
+                })*
+            };
+        }
+        overlay!(
+            output,
+            repository,
+            issues,
+            state,
+            batch_size,
+            skip_existing,
+            git_commit,
+            with_minimized_status,
+            format,
+            date_format,
+            timezone,
+            max_tokens,
+            keep_comments,
+            chunk_size,
+            chunk_overlap,
+            exclude_bots,
+            exclude_minimized,
+            download_assets,
+            combine,
+            cache_dir,
+            log_format,
+            filename_template,
+            prune,
+            prune_unavailable
+        );
+    }
+}
+
+/// Contents of a `config.toml` / `.ghif.toml` file:
+///
+/// ```toml
+/// [defaults]
+/// format = "xml"
+///
+/// [profiles.triage]
+/// state = "open"
+/// exclude-bots = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub defaults: Settings,
+    #[serde(default)]
+    pub profiles: HashMap<String, Settings>,
+}
+
+impl Config {
+    /// Loads the user config (`~/.config/ghif/config.toml`) and, if given, the
+    /// repository's `.ghif.toml`, with the repository file taking precedence.
+    pub fn discover(repo_root: Option<&Path>) -> Result<Self, GhError> {
+        let mut config = Config::default();
+        let paths = user_config_path()
+            .into_iter()
+            .chain(repo_root.map(|root| root.join(REPO_CONFIG_FILE)));
+
+        for path in paths {
+            if path.is_file() {
+                config.merge(Config::load(&path)?);
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, GhError> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content)
+            .map_err(|e| GhError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    fn merge(&mut self, other: Config) {
+        self.defaults.overlay(other.defaults);
+        for (name, settings) in other.profiles {
+            self.profiles.entry(name).or_default().overlay(settings);
+        }
+    }
+
+    /// The defaults with the named profile applied on top.
+    pub fn settings(&self, profile: Option<&str>) -> Result<Settings, GhError> {
+        let mut settings = self.defaults.clone();
+        if let Some(name) = profile {
+            let profile = self
+                .profiles
+                .get(name)
+                .ok_or_else(|| GhError::ConfigError(format!("Unknown profile: {}", name)))?;
+            settings.overlay(profile.clone());
+        }
+        Ok(settings)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/ghif/config.toml`, falling back to `~/.config/ghif/config.toml`.
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(config_dir.join("ghif").join("config.toml"))
+}
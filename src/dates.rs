@@ -0,0 +1,228 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// How timestamps are written in rendered output.
+#[derive(Debug, Clone)]
+pub enum DateStyle {
+    /// `2024-01-04 12:34:56 UTC`
+    Default,
+    /// `2024-01-04T12:34:56+00:00`
+    Rfc3339,
+    /// `3 days ago`
+    Relative,
+    /// A `strftime`-style pattern, e.g. `%Y-%m-%d %H:%M`
+    Custom(String),
+}
+
+impl FromStr for DateStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(DateStyle::Default),
+            "rfc3339" | "iso8601" => Ok(DateStyle::Rfc3339),
+            "relative" => Ok(DateStyle::Relative),
+            _ if s.contains('%') => {
+                if StrftimeItems::new(s).any(|item| matches!(item, Item::Error)) {
+                    Err(format!("Invalid date format pattern: {}", s))
+                } else {
+                    Ok(DateStyle::Custom(s.to_string()))
+                }
+            }
+            _ => Err(format!("Unsupported date format: {}", s)),
+        }
+    }
+}
+
+/// Time zone timestamps are shown in.
+#[derive(Debug, Clone, Copy)]
+pub enum Zone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "utc" | "z" => Ok(Zone::Utc),
+            "local" => Ok(Zone::Local),
+            _ => parse_offset(s)
+                .map(Zone::Fixed)
+                .ok_or_else(|| format!("Unsupported timezone: {} (use utc, local or +HH:MM)", s)),
+        }
+    }
+}
+
+/// Parses `+09:00`, `-0530` or `+9` style UTC offsets.
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.chars().next()? {
+        '+' => (1, &s[1..]),
+        '-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 && rest.is_char_boundary(2) => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    // Only digits, so signs and non-ASCII input are rejected rather than parsed
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !digits(hours) || !digits(minutes) {
+        return None;
+    }
+    let hours = hours.parse::<i32>().ok()?;
+    let minutes = minutes.parse::<i32>().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Renders timestamps consistently across writers.
+#[derive(Debug, Clone)]
+pub struct DateFormat {
+    style: DateStyle,
+    zone: Zone,
+    /// Reference point for relative dates, fixed so one run renders consistently
+    now: DateTime<Utc>,
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        DateFormat::new(DateStyle::Default, Zone::Utc)
+    }
+}
+
+impl DateFormat {
+    pub fn new(style: DateStyle, zone: Zone) -> Self {
+        DateFormat {
+            style,
+            zone,
+            now: Utc::now(),
+        }
+    }
+
+    pub fn render(&self, date: &DateTime<Utc>) -> String {
+        if let DateStyle::Relative = self.style {
+            return relative(date, &self.now);
+        }
+
+        match self.zone {
+            Zone::Utc => self.render_in(*date),
+            Zone::Local => self.render_in(date.with_timezone(&Local)),
+            Zone::Fixed(offset) => self.render_in(date.with_timezone(&offset)),
+        }
+    }
+
+    fn render_in<Tz: TimeZone>(&self, date: DateTime<Tz>) -> String
+    where
+        Tz::Offset: Display,
+    {
+        match &self.style {
+            DateStyle::Default | DateStyle::Relative => date.to_string(),
+            DateStyle::Rfc3339 => date.to_rfc3339(),
+            DateStyle::Custom(pattern) => date.format(pattern).to_string(),
+        }
+    }
+}
+
+fn relative(date: &DateTime<Utc>, now: &DateTime<Utc>) -> String {
+    let seconds = (*now - *date).num_seconds();
+    let (amount, unit) = match seconds.abs() {
+        s if s < 60 => return "just now".to_string(),
+        s if s < 3600 => (s / 60, "minute"),
+        s if s < 86400 => (s / 3600, "hour"),
+        s if s < 30 * 86400 => (s / 86400, "day"),
+        s if s < 365 * 86400 => (s / (30 * 86400), "month"),
+        s => (s / (365 * 86400), "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+
+    if seconds >= 0 {
+        format!("{} {}{} ago", amount, unit, plural)
+    } else {
+        format!("in {} {}{}", amount, unit, plural)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn offset(s: &str) -> Option<i32> {
+        parse_offset(s).map(|offset| offset.local_minus_utc())
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(offset("+09:00"), Some(9 * 3600));
+        assert_eq!(offset("-05:30"), Some(-(5 * 3600 + 30 * 60)));
+        assert_eq!(offset("+0530"), Some(5 * 3600 + 30 * 60));
+        assert_eq!(offset("+5"), Some(5 * 3600));
+    }
+
+    #[test]
+    fn rejects_invalid_offsets() {
+        for s in [
+            "", "09:00", "+", "+09:60", "+24:00", "+9:-5", "++5", "+-5", "+ab:cd",
+        ] {
+            assert_eq!(offset(s), None, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn rejects_non_ascii_offsets_without_panicking() {
+        for s in ["+1é3", "+é", "+éé", "-1é", "+09:é", "−05:00"] {
+            assert_eq!(offset(s), None, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn parses_zones() {
+        assert!(matches!("UTC".parse(), Ok(Zone::Utc)));
+        assert!(matches!("z".parse(), Ok(Zone::Utc)));
+        assert!(matches!("local".parse(), Ok(Zone::Local)));
+        assert!(matches!("+02:00".parse(), Ok(Zone::Fixed(_))));
+        assert!("Europe/Paris".parse::<Zone>().is_err());
+    }
+
+    #[test]
+    fn parses_styles() {
+        assert!(matches!("RFC3339".parse(), Ok(DateStyle::Rfc3339)));
+        assert!(matches!("iso8601".parse(), Ok(DateStyle::Rfc3339)));
+        assert!(matches!("%Y-%m-%d".parse(), Ok(DateStyle::Custom(_))));
+        assert!("%Y-%Q".parse::<DateStyle>().is_err());
+        assert!("yesterday".parse::<DateStyle>().is_err());
+    }
+
+    #[test]
+    fn renders_in_zone() {
+        let date = Utc.with_ymd_and_hms(2024, 1, 4, 23, 30, 0).unwrap();
+        let format = DateFormat::new(
+            DateStyle::Custom("%Y-%m-%d %H:%M".to_string()),
+            "+09:00".parse().unwrap(),
+        );
+        assert_eq!(format.render(&date), "2024-01-05 08:30");
+        let format = DateFormat::new(DateStyle::Rfc3339, Zone::Utc);
+        assert_eq!(format.render(&date), "2024-01-04T23:30:00+00:00");
+    }
+
+    #[test]
+    fn renders_relative_dates() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(relative(&(now - Duration::seconds(30)), &now), "just now");
+        assert_eq!(
+            relative(&(now - Duration::minutes(1)), &now),
+            "1 minute ago"
+        );
+        assert_eq!(relative(&(now - Duration::days(3)), &now), "3 days ago");
+        assert_eq!(relative(&(now + Duration::hours(2)), &now), "in 2 hours");
+        assert_eq!(relative(&(now - Duration::days(800)), &now), "2 years ago");
+    }
+}
@@ -0,0 +1,272 @@
+use crate::github::IssueData;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Similarity at or above which two issues are reported as likely duplicates.
+pub const DEFAULT_THRESHOLD: f64 = 0.5;
+
+/// Words too common in issue text to say anything about what an issue is about.
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at", "be",
+    "been", "but", "by", "can", "could", "did", "do", "does", "doesn", "don", "for", "from", "get",
+    "gets", "had", "has", "have", "how", "i", "if", "in", "into", "is", "isn", "it", "its", "just",
+    "me", "my", "no", "not", "of", "on", "or", "our", "should", "so", "some", "than", "that",
+    "the", "their", "them", "then", "there", "these", "this", "to", "too", "up", "us", "was", "we",
+    "were", "what", "when", "where", "which", "while", "will", "with", "would", "you", "your",
+];
+
+/// An issue in a cluster of likely duplicates.
+#[derive(Debug, Serialize)]
+pub struct ClusterIssue {
+    pub number: u64,
+    pub title: String,
+    pub state: String,
+    pub url: String,
+}
+
+/// The similarity of two issues in a cluster, from 0 (nothing in common) to 1.
+#[derive(Debug, Serialize)]
+pub struct SimilarPair {
+    pub issues: [u64; 2],
+    pub score: f64,
+}
+
+/// Issues linked by similarity above the threshold, directly or through each other.
+/// Issues are oldest first, as the oldest is usually the one to keep.
+#[derive(Debug, Serialize)]
+pub struct Cluster {
+    /// The highest similarity in the cluster
+    pub score: f64,
+    pub issues: Vec<ClusterIssue>,
+    pub pairs: Vec<SimilarPair>,
+}
+
+/// Finds clusters of likely duplicates by TF-IDF cosine similarity of the issues'
+/// titles and bodies, most similar first. Titles count twice, as they're the most
+/// telling part of an issue.
+pub fn find_duplicates(issues: &[&IssueData], threshold: f64) -> Vec<Cluster> {
+    let vectors = tf_idf(issues);
+    let pairs = similar_pairs(&vectors, threshold);
+
+    let mut parents = (0..issues.len()).collect::<Vec<_>>();
+    for &(a, b, _) in &pairs {
+        let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+        parents[a.max(b)] = a.min(b);
+    }
+
+    let mut members = HashMap::<usize, Vec<usize>>::new();
+    for &(a, b, _) in &pairs {
+        for i in [a, b] {
+            let group = members.entry(root(&mut parents, i)).or_default();
+            if !group.contains(&i) {
+                group.push(i);
+            }
+        }
+    }
+
+    let mut clusters = members
+        .into_values()
+        .map(|mut group| {
+            group.sort_by_key(|&i| issues[i].number());
+            let mut cluster_pairs = pairs
+                .iter()
+                .filter(|(a, _, _)| group.contains(a))
+                .map(|&(a, b, score)| {
+                    let (a, b) = (issues[a].number(), issues[b].number());
+                    SimilarPair {
+                        issues: [a.min(b), a.max(b)],
+                        score: round(score),
+                    }
+                })
+                .collect::<Vec<_>>();
+            cluster_pairs.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.issues.cmp(&b.issues)));
+            Cluster {
+                score: cluster_pairs
+                    .first()
+                    .map(|pair| pair.score)
+                    .unwrap_or_default(),
+                issues: group
+                    .iter()
+                    .map(|&i| ClusterIssue {
+                        number: issues[i].number(),
+                        title: issues[i].title().to_string(),
+                        state: issues[i].state().to_string(),
+                        url: issues[i].html_url().to_string(),
+                    })
+                    .collect(),
+                pairs: cluster_pairs,
+            }
+        })
+        .collect::<Vec<_>>();
+    clusters.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.issues[0].number.cmp(&b.issues[0].number))
+    });
+    clusters
+}
+
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+fn round(score: f64) -> f64 {
+    (score * 1000.0).round() / 1000.0
+}
+
+/// Lowercase words of letters and digits from any script, without stop words and
+/// single characters.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().nth(1).is_some())
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+/// A unit-length TF-IDF vector per issue, as (term, weight) pairs sorted by term.
+/// Term frequencies are dampened (1 + ln tf) so a word repeated throughout a long
+/// log doesn't dominate.
+fn tf_idf(issues: &[&IssueData]) -> Vec<Vec<(usize, f64)>> {
+    let mut terms = HashMap::<String, usize>::new();
+    let counts = issues
+        .iter()
+        .map(|issue| {
+            let mut counts = HashMap::<usize, f64>::new();
+            let title = words(issue.title()).collect::<Vec<_>>();
+            let body = words(issue.body().unwrap_or_default());
+            for word in title
+                .iter()
+                .cloned()
+                .chain(title.iter().cloned())
+                .chain(body)
+            {
+                let next = terms.len();
+                *counts
+                    .entry(*terms.entry(word).or_insert(next))
+                    .or_default() += 1.0;
+            }
+            counts
+        })
+        .collect::<Vec<_>>();
+
+    let mut document_frequency = vec![0usize; terms.len()];
+    for counts in &counts {
+        for &term in counts.keys() {
+            document_frequency[term] += 1;
+        }
+    }
+    let documents = issues.len() as f64;
+    let idf = document_frequency
+        .iter()
+        .map(|&df| ((1.0 + documents) / (1.0 + df as f64)).ln() + 1.0)
+        .collect::<Vec<_>>();
+
+    counts
+        .into_iter()
+        .map(|counts| {
+            let mut vector = counts
+                .into_iter()
+                .map(|(term, tf)| (term, (1.0 + tf.ln()) * idf[term]))
+                .collect::<Vec<_>>();
+            let norm = vector.iter().map(|(_, w)| w * w).sum::<f64>().sqrt();
+            for (_, weight) in &mut vector {
+                *weight /= norm;
+            }
+            vector.sort_by_key(|&(term, _)| term);
+            vector
+        })
+        .collect()
+}
+
+/// Pairs of issues (by index, lower first) with a cosine similarity of at least
+/// `threshold`. Scores are accumulated through an inverted index, so only issues
+/// sharing a word are compared.
+fn similar_pairs(vectors: &[Vec<(usize, f64)>], threshold: f64) -> Vec<(usize, usize, f64)> {
+    let mut postings = HashMap::<usize, Vec<(usize, f64)>>::new();
+    for (i, vector) in vectors.iter().enumerate() {
+        for &(term, weight) in vector {
+            postings.entry(term).or_default().push((i, weight));
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for (i, vector) in vectors.iter().enumerate() {
+        let mut scores = HashMap::<usize, f64>::new();
+        for (term, weight) in vector {
+            for &(j, other) in &postings[term] {
+                if j > i {
+                    *scores.entry(j).or_default() += weight * other;
+                }
+            }
+        }
+        pairs.extend(
+            scores
+                .into_iter()
+                .filter(|&(_, score)| score >= threshold)
+                .map(|(j, score)| (i, j, score.min(1.0))),
+        );
+    }
+    pairs
+}
+
+/// Plain text clusters for the terminal.
+pub fn render_table(clusters: &[Cluster]) -> String {
+    let mut out = format!("Clusters of likely duplicates: {}\n", clusters.len());
+    for (n, cluster) in clusters.iter().enumerate() {
+        out.push_str(&format!(
+            "\nCluster {} (similarity {:.2})\n",
+            n + 1,
+            cluster.score
+        ));
+        for issue in &cluster.issues {
+            out.push_str(&format!(
+                "  #{:<6} {:<7} {}\n",
+                issue.number, issue.state, issue.title
+            ));
+        }
+        for pair in &cluster.pairs {
+            out.push_str(&format!(
+                "    #{} ~ #{}  {:.2}\n",
+                pair.issues[0], pair.issues[1], pair.score
+            ));
+        }
+    }
+    out
+}
+
+/// A Markdown checklist per cluster, for a triage issue.
+pub fn render_markdown(clusters: &[Cluster]) -> String {
+    let mut out = format!(
+        "# Likely Duplicate Issues\n\nClusters found: {}\n",
+        clusters.len()
+    );
+    for (n, cluster) in clusters.iter().enumerate() {
+        out.push_str(&format!(
+            "\n## Cluster {} (similarity {:.2})\n\n",
+            n + 1,
+            cluster.score
+        ));
+        for issue in &cluster.issues {
+            out.push_str(&format!(
+                "- [ ] [#{}]({}) {} ({})\n",
+                issue.number, issue.url, issue.title, issue.state
+            ));
+        }
+        let pairs = cluster
+            .pairs
+            .iter()
+            .map(|pair| {
+                format!(
+                    "#{} ~ #{}: {:.2}",
+                    pair.issues[0], pair.issues[1], pair.score
+                )
+            })
+            .collect::<Vec<_>>();
+        out.push_str(&format!("\nSimilarity: {}\n", pairs.join(", ")));
+    }
+    out
+}
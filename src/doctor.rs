@@ -0,0 +1,389 @@
+use crate::auth::Credentials;
+use crate::error::GhError;
+use crate::github::{ApiProbe, GitHubClient};
+use chrono::{DateTime, Utc};
+use octocrab::models::Rate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// The outcome of one diagnostic, with a hint on how to fix it when it isn't ok.
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// Kind of token, from GitHub's token prefixes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Classic,
+    FineGrained,
+    OAuth,
+    UserToServer,
+    Installation,
+    Unknown,
+}
+
+impl TokenKind {
+    fn of(token: &str) -> Self {
+        match token {
+            t if t.starts_with("github_pat_") => TokenKind::FineGrained,
+            t if t.starts_with("ghp_") => TokenKind::Classic,
+            t if t.starts_with("gho_") => TokenKind::OAuth,
+            t if t.starts_with("ghu_") => TokenKind::UserToServer,
+            t if t.starts_with("ghs_") => TokenKind::Installation,
+            _ => TokenKind::Unknown,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            TokenKind::Classic => "classic personal access token",
+            TokenKind::FineGrained => "fine-grained personal access token",
+            TokenKind::OAuth => "OAuth token",
+            TokenKind::UserToServer => "GitHub App user token",
+            TokenKind::Installation => "GitHub App installation token",
+            TokenKind::Unknown => "token",
+        }
+    }
+
+    /// Whether access is governed by OAuth scopes (reported in `X-OAuth-Scopes`)
+    /// rather than per-repository permissions.
+    fn uses_scopes(self) -> bool {
+        matches!(self, TokenKind::Classic | TokenKind::OAuth)
+    }
+}
+
+/// Checks the credentials, the repository (if given) and the rate limits, and
+/// explains what's missing. Requests that fail outright (e.g. on a network error)
+/// become failed checks, so the checks before them are still reported.
+pub async fn run_checks(
+    client: &GitHubClient,
+    credentials: &Credentials,
+    repo: Option<(&str, &str)>,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let kind = match credentials {
+        Credentials::Token { token, source } => {
+            let kind = TokenKind::of(token);
+            checks.push(Check::ok(
+                "credentials",
+                format!("{} from {}", kind.describe(), source),
+            ));
+            check_token(client, kind, &mut checks).await;
+            Some(kind)
+        }
+        Credentials::App(app) => {
+            checks.push(Check::ok(
+                "credentials",
+                format!(
+                    "GitHub App {}; its permissions are set on the app installation",
+                    app.app_id
+                ),
+            ));
+            Some(TokenKind::Installation)
+        }
+        Credentials::Anonymous => {
+            checks.push(Check::warn(
+                "credentials",
+                "no token found; only public repositories are readable, at 60 requests per hour",
+                "Set GITHUB_TOKEN, pass --token, or log in with `gh auth login`",
+            ));
+            None
+        }
+    };
+
+    match repo {
+        Some((owner, repo)) => check_repository(client, kind, owner, repo, &mut checks).await,
+        None => checks.push(Check::warn(
+            "repository",
+            "no repository given or detected, so repository access wasn't checked",
+            "Pass -r owner/repo or run ghif doctor inside a clone",
+        )),
+    }
+
+    let resources = match client.rate_limits().await {
+        Ok(rate_limit) => rate_limit.resources,
+        Err(e) => {
+            checks.push(request_failed(
+                "rate limit",
+                format!("couldn't read rate limits: {}", e),
+            ));
+            return checks;
+        }
+    };
+    checks.push(check_rate("rate limit: core", &resources.core));
+    checks.push(check_rate("rate limit: search", &resources.search));
+    checks.push(match &resources.graphql {
+        Some(rate) => check_rate("rate limit: graphql", rate),
+        None => Check::warn(
+            "rate limit: graphql",
+            "unavailable without authentication",
+            "--exclude-minimized and --with-minimized-status use the GraphQL API, which requires a token",
+        ),
+    });
+
+    checks
+}
+
+/// The failed "credentials" check when no client could be set up from them, e.g.
+/// for a GitHub App that isn't installed on the repository.
+pub fn client_failed(credentials: &Credentials, error: &GhError) -> Check {
+    let hint = match credentials {
+        Credentials::App(_) => {
+            "Check the app ID and private key, and that the app is installed on the repository (or pass --app-installation-id)"
+        }
+        _ => "Check the token, then run ghif doctor again",
+    };
+    Check::fail("credentials", error.to_string(), hint)
+}
+
+/// A check whose request didn't get an answer from GitHub at all.
+fn request_failed(name: &'static str, detail: String) -> Check {
+    Check::fail(
+        name,
+        detail,
+        "Fix the checks above if any failed; otherwise check the network connection and https://www.githubstatus.com",
+    )
+}
+
+async fn check_token(client: &GitHubClient, kind: TokenKind, checks: &mut Vec<Check>) {
+    if kind == TokenKind::Installation {
+        // Installation tokens can't read /user
+        return;
+    }
+
+    let user = match client.probe("/user").await {
+        Ok(user) => user,
+        Err(e) => {
+            checks.push(request_failed(
+                "token",
+                format!("couldn't check the token: {}", e),
+            ));
+            return;
+        }
+    };
+    if user.status == 401 {
+        checks.push(Check::fail(
+            "token",
+            "GitHub rejected the token (invalid, expired or revoked)",
+            "Create a new token, or run `gh auth refresh` if it came from the gh CLI",
+        ));
+        return;
+    }
+    if !user.is_success() {
+        checks.push(Check::warn(
+            "token",
+            format!(
+                "couldn't read the token's user: {}",
+                describe_failure(&user)
+            ),
+            "The token may still work for repository access; see the checks below",
+        ));
+        return;
+    }
+
+    let login = user.body["login"].as_str().unwrap_or("unknown user");
+    let expiry = user
+        .header("github-authentication-token-expiration")
+        .map(|expiry| format!(", expires {}", expiry))
+        .unwrap_or_default();
+    checks.push(Check::ok(
+        "token",
+        format!("valid, authenticated as {}{}", login, expiry),
+    ));
+
+    if !kind.uses_scopes() {
+        if kind == TokenKind::FineGrained {
+            checks.push(Check::ok(
+                "permissions",
+                "fine-grained tokens have per-repository permissions; ghif needs Issues: Read-only and Metadata: Read-only",
+            ));
+        }
+        return;
+    }
+
+    let scopes = user
+        .header("x-oauth-scopes")
+        .map(|scopes| {
+            scopes
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let listed = if scopes.is_empty() {
+        "none".to_string()
+    } else {
+        scopes.join(", ")
+    };
+    checks.push(if scopes.iter().any(|s| s == "repo") {
+        Check::ok("scopes", listed)
+    } else {
+        Check::warn(
+            "scopes",
+            format!("{} (no `repo` scope, so private repositories aren't readable)", listed),
+            "Add the `repo` scope to read private repositories, or `gh auth refresh -s repo` for gh CLI tokens",
+        )
+    });
+}
+
+async fn check_repository(
+    client: &GitHubClient,
+    kind: Option<TokenKind>,
+    owner: &str,
+    repo: &str,
+    checks: &mut Vec<Check>,
+) {
+    let name = format!("{}/{}", owner, repo);
+    let repository = match client.probe(&format!("/repos/{}", name)).await {
+        Ok(repository) => repository,
+        Err(e) => {
+            checks.push(request_failed(
+                "repository",
+                format!("couldn't check {}: {}", name, e),
+            ));
+            return;
+        }
+    };
+
+    if !repository.is_success() {
+        let hint = match kind {
+            None => "If the repository is private, provide a token".to_string(),
+            Some(kind) if kind.uses_scopes() => {
+                "Check the name, and that the token's account can see it with the `repo` scope"
+                    .to_string()
+            }
+            Some(TokenKind::FineGrained) => {
+                "Check the name, and that the token's repository access includes it".to_string()
+            }
+            Some(TokenKind::Installation) => {
+                "Check the name, and that the GitHub App is installed on it".to_string()
+            }
+            Some(_) => "Check the name and that the token can access it".to_string(),
+        };
+        checks.push(Check::fail(
+            "repository",
+            format!("can't access {}: {}", name, describe_failure(&repository)),
+            hint,
+        ));
+        return;
+    }
+
+    let visibility = repository.body["visibility"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            if repository.body["private"].as_bool() == Some(true) {
+                "private".to_string()
+            } else {
+                "public".to_string()
+            }
+        });
+    checks.push(Check::ok(
+        "repository",
+        format!("{} is {}", name, visibility),
+    ));
+
+    if repository.body["has_issues"].as_bool() == Some(false) {
+        checks.push(Check::fail(
+            "issues",
+            format!("issues are disabled on {}", name),
+            "Enable issues in the repository settings; there's nothing to fetch otherwise",
+        ));
+        return;
+    }
+
+    let issues = match client
+        .probe(&format!("/repos/{}/issues?per_page=1&state=all", name))
+        .await
+    {
+        Ok(issues) => issues,
+        Err(e) => {
+            checks.push(request_failed(
+                "issues",
+                format!("couldn't list issues: {}", e),
+            ));
+            return;
+        }
+    };
+    checks.push(if issues.is_success() {
+        Check::ok("issues", "enabled and readable")
+    } else {
+        let required = issues
+            .header("x-accepted-github-permissions")
+            .map(|permissions| format!("Grant the token {}", permissions))
+            .unwrap_or_else(|| "Check the token's access to issues".to_string());
+        Check::fail(
+            "issues",
+            format!("can't list issues: {}", describe_failure(&issues)),
+            required,
+        )
+    });
+}
+
+/// Warns once less than a tenth of a bucket is left.
+fn check_rate(name: &'static str, rate: &Rate) -> Check {
+    let reset = DateTime::<Utc>::from_timestamp(rate.reset as i64, 0)
+        .map(|reset| reset.to_rfc3339())
+        .unwrap_or_default();
+    let detail = format!(
+        "{}/{} remaining, resets at {}",
+        rate.remaining, rate.limit, reset
+    );
+
+    if rate.remaining * 10 < rate.limit {
+        Check::warn(
+            name,
+            detail,
+            "Wait for the reset, or authenticate for a higher limit",
+        )
+    } else {
+        Check::ok(name, detail)
+    }
+}
+
+fn describe_failure(probe: &ApiProbe) -> String {
+    match probe.message() {
+        Some(message) => format!("HTTP {} ({})", probe.status, message),
+        None => format!("HTTP {}", probe.status),
+    }
+}
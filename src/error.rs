@@ -1,3 +1,5 @@
+use chrono::{DateTime, Utc};
+
 #[derive(Debug, thiserror::Error)]
 #[allow(dead_code)]
 pub enum GhError {
@@ -10,12 +12,36 @@ pub enum GhError {
     #[error("GitHub API error: {0}")]
     ApiError(String),
 
-    #[error("Rate limit exceeded. Reset at: {0}")]
-    RateLimitError(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Access denied: {0}")]
+    Forbidden(String),
+
+    #[error("Rate limit exceeded{}", reset_suffix(.reset))]
+    RateLimited { reset: Option<DateTime<Utc>> },
+
+    #[error("Secondary rate limit hit, retry after {retry_after} seconds")]
+    SecondaryLimit { retry_after: u64 },
+
+    #[error("Issues are disabled for {0}")]
+    IssuesDisabled(String),
+
+    #[error("Network error: {0}")]
+    Network(String),
 
     #[error("Invalid output format: {0}")]
     InvalidFormat(String),
 
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+
+    #[error("Invalid cache entry: {0}")]
+    CacheError(String),
+
+    #[error("Git command failed: {0}")]
+    GitError(String),
+
     #[error(transparent)]
     IoError(#[from] std::io::Error),
 
@@ -25,3 +51,70 @@ pub enum GhError {
     #[error(transparent)]
     TemplateError(#[from] indicatif::style::TemplateError),
 }
+
+fn reset_suffix(reset: &Option<DateTime<Utc>>) -> String {
+    reset
+        .map(|reset| format!(". Reset at: {}", reset.to_rfc3339()))
+        .unwrap_or_default()
+}
+
+impl GhError {
+    /// Process exit code, so scripts can tell "retry later" (7, 8) from
+    /// "misconfigured" (2-6) without parsing messages.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            GhError::InvalidRepoUrl(_)
+            | GhError::InvalidFormat(_)
+            | GhError::ConfigError(_)
+            | GhError::TemplateError(_) => 2,
+            GhError::AuthError(_) => 3,
+            GhError::NotFound(_) => 4,
+            GhError::Forbidden(_) => 5,
+            GhError::IssuesDisabled(_) => 6,
+            GhError::RateLimited { .. } | GhError::SecondaryLimit { .. } => 7,
+            GhError::Network(_) => 8,
+            GhError::ApiError(_)
+            | GhError::CacheError(_)
+            | GhError::GitError(_)
+            | GhError::IoError(_)
+            | GhError::OctocrabError(_) => 1,
+        }
+    }
+
+    /// Short machine-readable name of the variant, e.g. `not_found`, for reports and
+    /// JSON logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GhError::AuthError(_) => "auth",
+            GhError::InvalidRepoUrl(_) => "invalid_repo_url",
+            GhError::ApiError(_) => "api",
+            GhError::NotFound(_) => "not_found",
+            GhError::Forbidden(_) => "forbidden",
+            GhError::RateLimited { .. } => "rate_limited",
+            GhError::SecondaryLimit { .. } => "secondary_limit",
+            GhError::IssuesDisabled(_) => "issues_disabled",
+            GhError::Network(_) => "network",
+            GhError::InvalidFormat(_) => "invalid_format",
+            GhError::ConfigError(_) => "config",
+            GhError::CacheError(_) => "cache",
+            GhError::GitError(_) => "git",
+            GhError::IoError(_) => "io",
+            GhError::OctocrabError(_) => "octocrab",
+            GhError::TemplateError(_) => "template",
+        }
+    }
+
+    /// Whether the error concerns a single issue (deleted, private, transferred), so a
+    /// run can skip it and carry on with the rest.
+    pub fn is_issue_specific(&self) -> bool {
+        matches!(self, GhError::NotFound(_) | GhError::Forbidden(_))
+    }
+
+    /// Whether the same request may succeed later without any change.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            GhError::RateLimited { .. } | GhError::SecondaryLimit { .. } | GhError::Network(_)
+        )
+    }
+}
@@ -0,0 +1,68 @@
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Whether [`write_if_changed`] touched the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written,
+    /// The file already had this content, so it (and its mtime) was left alone
+    Unchanged,
+}
+
+/// A file written for an issue.
+#[derive(Debug, Clone)]
+pub struct SavedFile {
+    pub path: String,
+    pub outcome: WriteOutcome,
+}
+
+/// Writes `contents` to `path` through a temporary file in the same directory that's
+/// renamed into place, so readers (and an interrupted run) never see a partly
+/// written file.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    let result = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_ref())?;
+        file.sync_all()
+    });
+    match result.and_then(|()| std::fs::rename(&temp, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+/// Like [`write_atomic`], but skips the write when the SHA-256 of `contents` matches
+/// the file already on disk, preserving its mtime for tools that watch for changes.
+pub fn write_if_changed(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<WriteOutcome> {
+    let contents = contents.as_ref();
+    if is_unchanged(path, contents)? {
+        return Ok(WriteOutcome::Unchanged);
+    }
+    write_atomic(path, contents)?;
+    Ok(WriteOutcome::Written)
+}
+
+fn is_unchanged(path: &Path, contents: &[u8]) -> std::io::Result<bool> {
+    match std::fs::metadata(path) {
+        // A different size can't be the same content, so it isn't read
+        Ok(metadata) if metadata.len() != contents.len() as u64 => Ok(false),
+        Ok(_) => Ok(Sha256::digest(std::fs::read(path)?) == Sha256::digest(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// A hidden sibling of `path`, unique to this process, so it's skipped when scanning
+/// for issue files and two runs don't clobber each other's temporary files.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
@@ -1,10 +1,25 @@
+mod chunks;
+mod html;
+mod tabular;
+
+use crate::budget::Elision;
+use crate::dates::DateFormat;
 use crate::github::{CommentData, IssueData};
+use crate::references::{find_references, Origin, Reference};
 use std::str::FromStr;
 
+pub use chunks::ChunkWriter;
+pub use html::HtmlWriter;
+pub use tabular::TabularWriter;
+
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
     Markdown,
     Xml,
+    Chunks,
+    Html,
+    Csv,
+    Tsv,
 }
 
 impl FromStr for OutputFormat {
@@ -14,47 +29,157 @@ impl FromStr for OutputFormat {
         match s.to_lowercase().as_str() {
             "markdown" | "md" => Ok(OutputFormat::Markdown),
             "xml" => Ok(OutputFormat::Xml),
+            "chunks" | "jsonl" => Ok(OutputFormat::Chunks),
+            "html" => Ok(OutputFormat::Html),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
             _ => Err(format!("Unsupported format: {}", s)),
         }
     }
 }
 
+/// How the analysis commands (`stats`, `dedupe`) print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SummaryFormat {
+    #[default]
+    Table,
+    Json,
+    Markdown,
+}
+
+impl FromStr for SummaryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(SummaryFormat::Table),
+            "json" => Ok(SummaryFormat::Json),
+            "markdown" | "md" => Ok(SummaryFormat::Markdown),
+            _ => Err(format!(
+                "Unsupported format: {} (use table, json or markdown)",
+                s
+            )),
+        }
+    }
+}
+
+/// An issue listed in a collection-level file, with the path of its own file
+/// relative to the output directory. `None` when issues only go into combined
+/// files (`--combine`).
+pub struct IndexEntry<'a> {
+    pub issue: &'a IssueData,
+    pub path: Option<String>,
+}
+
 pub trait FormatWriter {
-    fn write_issue(&self, issue: &IssueData, comments: &[CommentData]) -> String;
+    fn write_issue(
+        &self,
+        issue: &IssueData,
+        comments: &[CommentData],
+        elision: Option<&Elision>,
+    ) -> String;
+
+    /// Joins several rendered issues into a single document.
+    fn combine(&self, issues: &[&str]) -> String {
+        issues.join("\n---\n\n")
+    }
+
+    /// Renders a collection-level file (e.g. an index page) covering all issues, if
+    /// the format has one.
+    fn write_index(&self, _entries: &[IndexEntry]) -> Option<String> {
+        None
+    }
+
+    /// Whether the format writes one file per issue, rather than only the index file.
+    fn writes_issue_files(&self) -> bool {
+        true
+    }
 }
 
-pub struct MarkdownWriter;
+pub struct MarkdownWriter {
+    pub dates: DateFormat,
+}
 
 impl FormatWriter for MarkdownWriter {
-    fn write_issue(&self, issue: &IssueData, comments: &[CommentData]) -> String {
-        let mut content = format!(
-            "# Issue #{}: {}\n\n\
-             **State:** {}\n\
-             **Created:** {}\n\
-             **Updated:** {}\n\
-             **Labels:** {}\n\
-             **Assignees:** {}\n\
-             **User:** {}\n\n\
-             ## Description\n\n\
-             {}\n",
-            issue.number(),
-            issue.title(),
-            issue.state(),
-            issue.created_at(),
-            issue.updated_at(),
-            issue.labels().join(", "),
-            issue.assignees().join(", "),
-            issue.user(),
+    fn write_issue(
+        &self,
+        issue: &IssueData,
+        comments: &[CommentData],
+        elision: Option<&Elision>,
+    ) -> String {
+        let mut content = format!("# Issue #{}: {}\n\n", issue.number(), issue.title());
+
+        let mut fields = vec![("State", issue.state().to_string())];
+        if let Some(reason) = issue.state_reason() {
+            fields.push(("State Reason", reason.to_string()));
+        }
+        fields.push(("Created", self.dates.render(issue.created_at())));
+        fields.push(("Updated", self.dates.render(issue.updated_at())));
+        if let Some(closed_at) = issue.closed_at() {
+            fields.push(("Closed", self.dates.render(closed_at)));
+        }
+        if let Some(closed_by) = issue.closed_by() {
+            fields.push(("Closed By", closed_by.to_string()));
+        }
+        if let Some(milestone) = issue.milestone() {
+            fields.push(("Milestone", milestone.to_string()));
+        }
+        fields.push(("Labels", issue.labels().join(", ")));
+        fields.push(("Assignees", issue.assignees().join(", ")));
+        fields.push(("User", issue.user().to_string()));
+        fields.push(("Author Association", issue.author_association().to_string()));
+        if issue.locked() {
+            let locked = match issue.active_lock_reason() {
+                Some(reason) => format!("yes ({})", reason),
+                None => "yes".to_string(),
+            };
+            fields.push(("Locked", locked));
+        }
+        fields.push(("Comments", issue.comment_count().to_string()));
+        fields.push(("URL", issue.html_url().to_string()));
+
+        for (name, value) in fields {
+            content.push_str(&format!("**{}:** {}\n", name, value));
+        }
+        content.push_str(&format!(
+            "\n## Description\n\n{}\n",
             issue.body().unwrap_or("*No description provided*")
-        );
+        ));
 
-        if !comments.is_empty() {
+        if !comments.is_empty() || elision.is_some() {
             content.push_str("\n## Comments\n\n");
-            for comment in comments {
-                content.push_str(&format!(
-                    "### @{} ({})\n\n{}\n\n",
-                    comment.user, comment.created_at, comment.body
-                ));
+            for i in 0..=comments.len() {
+                if let Some(elision) = elision.filter(|e| e.position == i) {
+                    content.push_str(&format!(
+                        "*... {} comments omitted ...*\n\n",
+                        elision.omitted
+                    ));
+                }
+                if let Some(comment) = comments.get(i) {
+                    content.push_str(&format!(
+                        "### @{} ({})\n\n*{}*\n\n{}\n\n",
+                        comment.user,
+                        self.dates.render(&comment.created_at),
+                        markdown_comment_details(comment, &self.dates),
+                        comment.body
+                    ));
+                }
+            }
+        }
+
+        let related = related_links(issue, comments);
+        if !related.is_empty() {
+            if !content.ends_with("\n\n") {
+                content.push('\n');
+            }
+            content.push_str("## Related\n\n");
+            for link in related {
+                let label = if link.commit {
+                    format!("`{}`", link.label)
+                } else {
+                    link.label
+                };
+                content.push_str(&format!("- [{}]({})\n", label, link.url));
             }
         }
 
@@ -62,10 +187,17 @@ impl FormatWriter for MarkdownWriter {
     }
 }
 
-pub struct XmlWriter;
+pub struct XmlWriter {
+    pub dates: DateFormat,
+}
 
 impl FormatWriter for XmlWriter {
-    fn write_issue(&self, issue: &IssueData, comments: &[CommentData]) -> String {
+    fn write_issue(
+        &self,
+        issue: &IssueData,
+        comments: &[CommentData],
+        elision: Option<&Elision>,
+    ) -> String {
         let mut content = format!(
             r#"<?xml version="1.0" encoding="UTF-8"?>
 <issue>
@@ -81,14 +213,14 @@ impl FormatWriter for XmlWriter {
         {}
     </assignees>
     <user>{}</user>
-    <description><![CDATA[
+{}    <description><![CDATA[
         {}
     ]]></description>"#,
             issue.number(),
             issue.title(),
             issue.state(),
-            issue.created_at(),
-            issue.updated_at(),
+            self.dates.render(issue.created_at()),
+            self.dates.render(issue.updated_at()),
             issue
                 .labels()
                 .iter()
@@ -102,34 +234,244 @@ impl FormatWriter for XmlWriter {
                 .collect::<Vec<_>>()
                 .join("\n"),
             issue.user(),
+            xml_details(issue, &self.dates),
             issue.body().unwrap_or("No description provided"),
         );
 
-        if !comments.is_empty() {
+        if !comments.is_empty() || elision.is_some() {
             content.push_str("\n    <comments>\n");
-            for comment in comments {
-                content.push_str(&format!(
-                    r#"        <comment>
+            for i in 0..=comments.len() {
+                if let Some(elision) = elision.filter(|e| e.position == i) {
+                    content.push_str(&format!(
+                        "        <omitted count=\"{}\"/>\n",
+                        elision.omitted
+                    ));
+                }
+                if let Some(comment) = comments.get(i) {
+                    content.push_str(&format!(
+                        r#"        <comment id="{}">
             <user>{}</user>
             <created_at>{}</created_at>
-            <body><![CDATA[{}]]></body>
+{}            <body><![CDATA[{}]]></body>
         </comment>
 "#,
-                    comment.user, comment.created_at, comment.body
-                ));
+                        comment.id,
+                        comment.user,
+                        self.dates.render(&comment.created_at),
+                        xml_comment_details(comment, &self.dates),
+                        comment.body
+                    ));
+                }
             }
             content.push_str("    </comments>\n");
         }
 
+        let related = related_links(issue, comments);
+        if !related.is_empty() {
+            content.push_str("    <related>\n");
+            for link in related {
+                let (element, attribute, value) = match &link.reference {
+                    Reference::Issue { number, .. } => ("issue", "number", number.to_string()),
+                    Reference::Commit { sha, .. } => ("commit", "sha", sha.clone()),
+                };
+                content.push_str(&format!(
+                    "        <{} repository=\"{}\" {}=\"{}\">{}</{}>\n",
+                    element,
+                    link.reference.repository(),
+                    attribute,
+                    value,
+                    link.url,
+                    element
+                ));
+            }
+            content.push_str("    </related>\n");
+        }
+
         content.push_str("</issue>\n");
         content
     }
+
+    fn combine(&self, issues: &[&str]) -> String {
+        let mut content = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<issues>\n");
+        for issue in issues {
+            let body = issue
+                .strip_prefix(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+                .unwrap_or(issue);
+            content.push_str(body.trim_start());
+        }
+        content.push_str("</issues>\n");
+        content
+    }
 }
 
-pub fn get_writer(format: OutputFormat) -> Box<dyn FormatWriter> {
+/// Settings for writers that need more than the output format itself.
+#[derive(Debug, Clone)]
+pub struct WriterOptions {
+    /// Maximum chunk length in characters for the chunks format
+    pub chunk_size: usize,
+    /// Characters shared between consecutive chunks for the chunks format
+    pub chunk_overlap: usize,
+    /// How timestamps are rendered
+    pub dates: DateFormat,
+    /// What links from an issue file need in front to reach the output directory
+    /// (e.g. `../` when issues are written into subdirectories)
+    pub link_prefix: String,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            chunk_size: 1000,
+            chunk_overlap: 200,
+            dates: DateFormat::default(),
+            link_prefix: String::new(),
+        }
+    }
+}
+
+/// An issue, pull request or commit mentioned in an issue's thread, labelled as
+/// GitHub shows it from the issue's repository.
+pub(crate) struct RelatedLink {
+    pub reference: Reference,
+    pub label: String,
+    pub url: String,
+    pub commit: bool,
+}
+
+/// The references in an issue's description and comments, for the "Related"
+/// section of the formats that have one.
+pub(crate) fn related_links(issue: &IssueData, comments: &[CommentData]) -> Vec<RelatedLink> {
+    let Some(origin) = Origin::of(issue) else {
+        return Vec::new();
+    };
+    find_references(issue, comments)
+        .into_iter()
+        .map(|reference| RelatedLink {
+            label: reference.label(&origin.repository),
+            url: reference.url(&origin.host),
+            commit: matches!(reference, Reference::Commit { .. }),
+            reference,
+        })
+        .collect()
+}
+
+/// Elements for the issue metadata beyond the basics, omitting ones that don't apply.
+fn xml_details(issue: &IssueData, dates: &DateFormat) -> String {
+    let mut details = String::new();
+    if let Some(reason) = issue.state_reason() {
+        details.push_str(&format!("    <state_reason>{}</state_reason>\n", reason));
+    }
+    if let Some(closed_at) = issue.closed_at() {
+        details.push_str(&format!(
+            "    <closed_at>{}</closed_at>\n",
+            dates.render(closed_at)
+        ));
+    }
+    if let Some(closed_by) = issue.closed_by() {
+        details.push_str(&format!("    <closed_by>{}</closed_by>\n", closed_by));
+    }
+    if let Some(milestone) = issue.milestone() {
+        details.push_str(&format!(
+            "    <milestone><![CDATA[{}]]></milestone>\n",
+            milestone
+        ));
+    }
+    details.push_str(&format!(
+        "    <author_association>{}</author_association>\n",
+        issue.author_association()
+    ));
+    match (issue.locked(), issue.active_lock_reason()) {
+        (true, Some(reason)) => details.push_str(&format!(
+            "    <locked reason=\"{}\">true</locked>\n",
+            reason
+        )),
+        (true, None) => details.push_str("    <locked>true</locked>\n"),
+        (false, _) => {}
+    }
+    details.push_str(&format!(
+        "    <comment_count>{}</comment_count>\n",
+        issue.comment_count()
+    ));
+    details.push_str(&format!("    <url>{}</url>\n", issue.html_url()));
+    details
+}
+
+/// One-line summary of a comment's metadata, e.g. `MEMBER · edited 2024-01-02 · [link](...)`.
+fn markdown_comment_details(comment: &CommentData, dates: &DateFormat) -> String {
+    let mut details = vec![comment.author_association.clone()];
+    if comment.is_bot {
+        details.push("bot".to_string());
+    }
+    if comment.edited {
+        match &comment.updated_at {
+            Some(updated_at) => details.push(format!("edited {}", dates.render(updated_at))),
+            None => details.push("edited".to_string()),
+        }
+    }
+    if comment.minimized {
+        match &comment.minimized_reason {
+            Some(reason) => details.push(format!("hidden as {}", reason.to_lowercase())),
+            None => details.push("hidden".to_string()),
+        }
+    }
+    details.push(format!("[link]({})", comment.html_url));
+    details.join(" · ")
+}
+
+fn xml_comment_details(comment: &CommentData, dates: &DateFormat) -> String {
+    let mut details = String::new();
+    if let Some(updated_at) = comment.updated_at.as_ref().filter(|_| comment.edited) {
+        details.push_str(&format!(
+            "            <updated_at>{}</updated_at>\n",
+            dates.render(updated_at)
+        ));
+    }
+    details.push_str(&format!(
+        "            <author_association>{}</author_association>\n",
+        comment.author_association
+    ));
+    details.push_str(&format!(
+        "            <edited>{}</edited>\n",
+        comment.edited
+    ));
+    details.push_str(&format!("            <bot>{}</bot>\n", comment.is_bot));
+    match (comment.minimized, &comment.minimized_reason) {
+        (true, Some(reason)) => details.push_str(&format!(
+            "            <minimized reason=\"{}\">true</minimized>\n",
+            reason
+        )),
+        (true, None) => details.push_str("            <minimized>true</minimized>\n"),
+        (false, _) => {}
+    }
+    details.push_str(&format!("            <url>{}</url>\n", comment.html_url));
+    details
+}
+
+pub fn get_writer(format: OutputFormat, options: &WriterOptions) -> Box<dyn FormatWriter> {
     match format {
-        OutputFormat::Markdown => Box::new(MarkdownWriter),
-        OutputFormat::Xml => Box::new(XmlWriter),
+        OutputFormat::Markdown => Box::new(MarkdownWriter {
+            dates: options.dates.clone(),
+        }),
+        OutputFormat::Xml => Box::new(XmlWriter {
+            dates: options.dates.clone(),
+        }),
+        OutputFormat::Chunks => Box::new(ChunkWriter {
+            size: options.chunk_size,
+            overlap: options.chunk_overlap,
+            dates: options.dates.clone(),
+        }),
+        OutputFormat::Html => Box::new(HtmlWriter {
+            dates: options.dates.clone(),
+            link_prefix: options.link_prefix.clone(),
+        }),
+        OutputFormat::Csv => Box::new(TabularWriter {
+            delimiter: b',',
+            dates: options.dates.clone(),
+        }),
+        OutputFormat::Tsv => Box::new(TabularWriter {
+            delimiter: b'\t',
+            dates: options.dates.clone(),
+        }),
     }
 }
 
@@ -137,5 +479,16 @@ pub fn get_file_extension(format: OutputFormat) -> &'static str {
     match format {
         OutputFormat::Markdown => "md",
         OutputFormat::Xml => "xml",
+        OutputFormat::Chunks => "jsonl",
+        OutputFormat::Html => "html",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Tsv => "tsv",
+    }
+}
+
+pub fn get_index_file_name(format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Csv | OutputFormat::Tsv => format!("issues.{}", get_file_extension(format)),
+        _ => format!("index.{}", get_file_extension(format)),
     }
 }
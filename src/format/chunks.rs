@@ -0,0 +1,266 @@
+use super::FormatWriter;
+use crate::budget::Elision;
+use crate::dates::DateFormat;
+use crate::github::{CommentData, IssueData};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Writes each issue as JSONL records of overlapping, size-bounded text chunks,
+/// ready for an embedding/vector store loader.
+pub struct ChunkWriter {
+    /// Maximum chunk length in characters
+    pub size: usize,
+    /// Characters shared between consecutive chunks
+    pub overlap: usize,
+    pub dates: DateFormat,
+}
+
+#[derive(Serialize)]
+struct ChunkRecord<'a> {
+    id: String,
+    issue: u64,
+    title: &'a str,
+    /// `None` for the issue description, otherwise the comment's position in the thread
+    comment_index: Option<usize>,
+    chunk_index: usize,
+    author: &'a str,
+    timestamp: String,
+    url: &'a str,
+    text: &'a str,
+}
+
+/// A piece of an issue thread (description or comment) to be chunked.
+struct Source<'a> {
+    comment_index: Option<usize>,
+    author: &'a str,
+    timestamp: &'a DateTime<Utc>,
+    url: &'a str,
+    text: &'a str,
+}
+
+impl ChunkWriter {
+    fn push_records(&self, out: &mut String, issue: &IssueData, source: Source) {
+        for (chunk_index, text) in split_text(source.text, self.size, self.overlap)
+            .into_iter()
+            .enumerate()
+        {
+            let record = ChunkRecord {
+                id: chunk_id(
+                    source.url,
+                    issue.number(),
+                    source.comment_index.is_some(),
+                    chunk_index,
+                ),
+                issue: issue.number(),
+                title: issue.title(),
+                comment_index: source.comment_index,
+                chunk_index,
+                author: source.author,
+                timestamp: self.dates.render(source.timestamp),
+                url: source.url,
+                text,
+            };
+            if let Ok(line) = serde_json::to_string(&record) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+impl FormatWriter for ChunkWriter {
+    fn write_issue(
+        &self,
+        issue: &IssueData,
+        comments: &[CommentData],
+        elision: Option<&Elision>,
+    ) -> String {
+        let mut content = String::new();
+        let description = format!("{}\n\n{}", issue.title(), issue.body().unwrap_or_default());
+        self.push_records(
+            &mut content,
+            issue,
+            Source {
+                comment_index: None,
+                author: issue.user(),
+                timestamp: issue.created_at(),
+                url: issue.html_url(),
+                text: &description,
+            },
+        );
+
+        for (i, comment) in comments.iter().enumerate() {
+            // Keep indices pointing at the original thread position when comments were elided
+            let index = match elision {
+                Some(elision) if i >= elision.position => i + elision.omitted,
+                _ => i,
+            };
+            self.push_records(
+                &mut content,
+                issue,
+                Source {
+                    comment_index: Some(index),
+                    author: &comment.user,
+                    timestamp: &comment.created_at,
+                    url: &comment.html_url,
+                    text: &comment.body,
+                },
+            );
+        }
+
+        content
+    }
+
+    fn combine(&self, issues: &[&str]) -> String {
+        issues.concat()
+    }
+}
+
+/// Stable identifier for a chunk, derived from its place in the thread rather than its
+/// text so that re-exports of an edited comment replace the old vectors instead of adding
+/// new ones. Comments are keyed on their own URL, which holds their id, not on their
+/// position, so hidden, deleted or excluded comments don't shift the IDs of later ones.
+fn chunk_id(url: &str, issue: u64, is_comment: bool, chunk_index: usize) -> String {
+    let key = if is_comment {
+        format!("{}/chunk-{}", url, chunk_index)
+    } else {
+        format!("{}#{}/body/chunk-{}", url, issue, chunk_index)
+    };
+    let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+    digest[..16].to_string()
+}
+
+/// Splits text into chunks of at most `size` characters, with `overlap` characters
+/// repeated between neighbours. Breaks on whitespace when one is available in the
+/// second half of the window.
+pub fn split_text(text: &str, size: usize, overlap: usize) -> Vec<&str> {
+    let bounds = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect::<Vec<_>>();
+    let len = bounds.len() - 1;
+    let size = size.max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < len {
+        let mut end = (start + size).min(len);
+        if end < len {
+            let window = &text[bounds[start + size / 2]..bounds[end]];
+            if let Some(pos) = window.rfind(char::is_whitespace) {
+                let byte = bounds[start + size / 2] + pos;
+                end = bounds.partition_point(|&b| b <= byte);
+            }
+        }
+
+        let chunk = &text[bounds[start]..bounds[end]];
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk);
+        }
+        if end == len {
+            break;
+        }
+        start = if end > start + overlap {
+            end - overlap
+        } else {
+            end
+        };
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::{DateStyle, Zone};
+    use serde_json::json;
+
+    fn issue() -> IssueData {
+        serde_json::from_value(json!({
+            "number": 12, "title": "Crash", "state": "open", "body": "It crashes.",
+            "labels": [], "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z", "closed_at": null, "assignees": [],
+            "user": "octocat", "html_url": "https://github.com/owner/repo/issues/12",
+            "comments_url": null, "comment_count": 2, "state_reason": null,
+            "closed_by": null, "milestone": null, "locked": false,
+            "active_lock_reason": null, "author_association": "NONE"
+        }))
+        .unwrap()
+    }
+
+    fn comment(id: u64, body: &str) -> CommentData {
+        serde_json::from_value(json!({
+            "id": id,
+            "html_url": format!("https://github.com/owner/repo/issues/12#issuecomment-{}", id),
+            "user": "hubot", "body": body, "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": null, "author_association": "NONE", "edited": false,
+            "is_bot": false, "minimized": false, "minimized_reason": null
+        }))
+        .unwrap()
+    }
+
+    fn ids(comments: &[CommentData]) -> Vec<String> {
+        let writer = ChunkWriter {
+            size: 1000,
+            overlap: 200,
+            dates: DateFormat::new(DateStyle::Default, Zone::Utc),
+        };
+        writer
+            .write_issue(&issue(), comments, None)
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn comment_ids_survive_earlier_comments_being_left_out() {
+        let all = ids(&[comment(1, "Bot noise"), comment(2, "Real reply")]);
+        let filtered = ids(&[comment(2, "Real reply")]);
+        assert_eq!(all.len(), 3);
+        assert_eq!(filtered, [all[0].clone(), all[2].clone()]);
+    }
+
+    #[test]
+    fn ids_ignore_edits() {
+        assert_eq!(ids(&[comment(1, "Before")]), ids(&[comment(1, "After")]));
+    }
+
+    #[test]
+    fn chunk_ids_differ_per_chunk_and_part() {
+        let url = "https://github.com/owner/repo/issues/12";
+        assert_ne!(chunk_id(url, 12, false, 0), chunk_id(url, 12, false, 1));
+        assert_ne!(chunk_id(url, 12, false, 0), chunk_id(url, 12, true, 0));
+    }
+
+    #[test]
+    fn splits_with_overlap_on_whitespace() {
+        let chunks = split_text("one two three four five six", 10, 4);
+        assert_eq!(
+            chunks,
+            [
+                "one two ",
+                "two three ",
+                "ree four ",
+                "our five ",
+                "ive six"
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_multibyte_text_on_char_boundaries() {
+        let chunks = split_text("ééééééééé", 4, 1);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 4));
+        assert_eq!(chunks.concat().chars().filter(|&c| c == 'é').count(), 11);
+    }
+
+    #[test]
+    fn short_and_blank_text() {
+        assert_eq!(split_text("short", 10, 2), ["short"]);
+        assert!(split_text("   ", 10, 2).is_empty());
+        assert!(split_text("", 10, 2).is_empty());
+    }
+}
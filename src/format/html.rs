@@ -0,0 +1,380 @@
+use super::{related_links, FormatWriter, IndexEntry};
+use crate::budget::Elision;
+use crate::dates::DateFormat;
+use crate::github::{CommentData, IssueData};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// Renders issues as standalone HTML pages plus a sortable, filterable index page,
+/// styled after the project's `pages/` site.
+pub struct HtmlWriter {
+    pub dates: DateFormat,
+    /// Prepended to the link back to `index.html` from issue pages
+    pub link_prefix: String,
+}
+
+const STYLE: &str = r#"
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, "Helvetica Neue", Arial, sans-serif;
+            line-height: 1.6;
+            margin: 0;
+            color: #333;
+        }
+        header {
+            background-color: #24292e;
+            color: white;
+            padding: 1.5rem 0;
+        }
+        header a { color: white; }
+        .container {
+            max-width: 900px;
+            margin: 0 auto;
+            padding: 0 2rem;
+        }
+        main.container { padding-top: 1.5rem; padding-bottom: 3rem; }
+        .meta { color: #586069; font-size: 0.9rem; }
+        .state { font-weight: bold; text-transform: capitalize; }
+        .state-open { color: #22863a; }
+        .state-closed { color: #cb2431; }
+        .label {
+            display: inline-block;
+            padding: 0 0.5rem;
+            margin: 0 0.2rem 0.2rem 0;
+            border-radius: 1rem;
+            background: #e1e4e8;
+            color: #24292e;
+            font-size: 0.8rem;
+            cursor: pointer;
+        }
+        .comment, .description {
+            background: #f6f8fa;
+            border-radius: 8px;
+            padding: 0.5rem 1.5rem;
+            margin-bottom: 1rem;
+        }
+        .omitted { color: #586069; font-style: italic; }
+        pre { overflow-x: auto; background: #eaeef2; padding: 0.8rem; border-radius: 6px; }
+        table { border-collapse: collapse; width: 100%; }
+        th, td { text-align: left; padding: 0.4rem; border-bottom: 1px solid #e1e4e8; }
+        th { cursor: pointer; user-select: none; }
+        .filters { display: flex; gap: 0.5rem; margin-bottom: 1rem; }
+        .filters input { flex: 1; padding: 0.4rem; }
+"#;
+
+const INDEX_SCRIPT: &str = r##"
+        const rows = Array.from(document.querySelectorAll("#issues tbody tr"));
+        const search = document.getElementById("search");
+        const state = document.getElementById("state");
+        let label = null;
+
+        function applyFilters() {
+            const query = search.value.toLowerCase();
+            for (const row of rows) {
+                const visible = row.dataset.text.includes(query)
+                    && (!state.value || row.dataset.state === state.value)
+                    && (!label || row.dataset.labels.split("\n").includes(label));
+                row.hidden = !visible;
+            }
+        }
+
+        search.addEventListener("input", applyFilters);
+        state.addEventListener("change", applyFilters);
+        document.querySelectorAll("#issues .label").forEach((badge) => {
+            badge.addEventListener("click", () => {
+                label = label === badge.textContent ? null : badge.textContent;
+                applyFilters();
+            });
+        });
+
+        document.querySelectorAll("#issues th").forEach((th, column) => {
+            th.addEventListener("click", () => {
+                const ascending = th.dataset.order !== "asc";
+                th.dataset.order = ascending ? "asc" : "desc";
+                const key = (row) => row.children[column].dataset.sort || row.children[column].textContent;
+                rows.sort((a, b) => {
+                    const [x, y] = [key(a), key(b)];
+                    const order = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+                    return ascending ? order : -order;
+                });
+                const tbody = document.querySelector("#issues tbody");
+                rows.forEach((row) => tbody.appendChild(row));
+            });
+        });
+"##;
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders GitHub-flavored Markdown to HTML. Raw HTML in the source is shown as
+/// text rather than passed through, and script links are dropped, since issue
+/// content is untrusted.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let parser = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(raw) => Event::Text(raw),
+        Event::Start(Tag::Link(kind, dest, title)) if is_script_url(&dest) => {
+            Event::Start(Tag::Link(kind, CowStr::Borrowed("#"), title))
+        }
+        event => event,
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, parser);
+    output
+}
+
+fn is_script_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("javascript:") || url.starts_with("vbscript:") || url.starts_with("data:text")
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{}</title>
+    <style>{}</style>
+</head>
+<body>
+{}</body>
+</html>
+"#,
+        escape_html(title),
+        STYLE,
+        body
+    )
+}
+
+/// Closing, milestone and lock details for the page's meta line.
+fn html_details(issue: &IssueData, dates: &DateFormat) -> String {
+    let mut details = String::new();
+    if let Some(closed_at) = issue.closed_at() {
+        details.push_str(&format!(
+            "\n        &middot; closed {}",
+            escape_html(&dates.render(closed_at))
+        ));
+        if let Some(closed_by) = issue.closed_by() {
+            details.push_str(&format!(" by <strong>{}</strong>", escape_html(closed_by)));
+        }
+        if let Some(reason) = issue.state_reason() {
+            details.push_str(&format!(" as {}", escape_html(&reason.replace('_', " "))));
+        }
+    }
+    if let Some(milestone) = issue.milestone() {
+        details.push_str(&format!(
+            "\n        &middot; milestone {}",
+            escape_html(milestone)
+        ));
+    }
+    if issue.locked() {
+        details.push_str("\n        &middot; locked");
+        if let Some(reason) = issue.active_lock_reason() {
+            details.push_str(&format!(" ({})", escape_html(reason)));
+        }
+    }
+    details
+}
+
+fn label_badges(labels: &[String]) -> String {
+    labels
+        .iter()
+        .map(|l| format!(r#"<span class="label">{}</span>"#, escape_html(l)))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+impl FormatWriter for HtmlWriter {
+    fn write_issue(
+        &self,
+        issue: &IssueData,
+        comments: &[CommentData],
+        elision: Option<&Elision>,
+    ) -> String {
+        let mut body = format!(
+            r#"<header>
+    <div class="container"><a href="{}index.html">&larr; All issues</a></div>
+</header>
+<main class="container">
+<article>
+    <h1>#{} {}</h1>
+    <p class="meta">
+        <span class="state state-{}">{}</span>
+        &middot; opened by <strong>{}</strong> on {}
+        &middot; updated {}{}
+    </p>
+    <p>{}</p>
+    <p class="meta">Assignees: {}</p>
+    <section class="description">
+{}    </section>
+"#,
+            escape_html(&self.link_prefix),
+            issue.number(),
+            escape_html(issue.title()),
+            escape_html(issue.state()),
+            escape_html(issue.state()),
+            escape_html(issue.user()),
+            escape_html(&self.dates.render(issue.created_at())),
+            escape_html(&self.dates.render(issue.updated_at())),
+            html_details(issue, &self.dates),
+            label_badges(issue.labels()),
+            escape_html(&issue.assignees().join(", ")),
+            markdown_to_html(issue.body().unwrap_or("*No description provided*")),
+        );
+
+        if !comments.is_empty() || elision.is_some() {
+            body.push_str("    <h2>Comments</h2>\n");
+            for i in 0..=comments.len() {
+                if let Some(elision) = elision.filter(|e| e.position == i) {
+                    body.push_str(&format!(
+                        "    <p class=\"omitted\">... {} comments omitted ...</p>\n",
+                        elision.omitted
+                    ));
+                }
+                if let Some(comment) = comments.get(i) {
+                    body.push_str(&format!(
+                        r#"    <section class="comment" id="comment-{}">
+        <p class="meta"><strong>@{}</strong> <span class="label">{}</span> <a href="{}">commented</a> on {}{}</p>
+{}    </section>
+"#,
+                        comment.id,
+                        escape_html(&comment.user),
+                        escape_html(&comment.author_association),
+                        escape_html(&comment.html_url),
+                        escape_html(&self.dates.render(&comment.created_at)),
+                        if comment.edited { " &middot; edited" } else { "" },
+                        markdown_to_html(&comment.body)
+                    ));
+                }
+            }
+        }
+        let related = related_links(issue, comments);
+        if !related.is_empty() {
+            body.push_str("    <h2>Related</h2>\n    <ul class=\"related\">\n");
+            for link in related {
+                let label = if link.commit {
+                    format!("<code>{}</code>", escape_html(&link.label))
+                } else {
+                    escape_html(&link.label)
+                };
+                body.push_str(&format!(
+                    "        <li><a href=\"{}\">{}</a></li>\n",
+                    escape_html(&link.url),
+                    label
+                ));
+            }
+            body.push_str("    </ul>\n");
+        }
+        body.push_str("</article>\n</main>\n");
+
+        page(&format!("#{} {}", issue.number(), issue.title()), &body)
+    }
+
+    fn combine(&self, issues: &[&str]) -> String {
+        let articles = issues
+            .iter()
+            .filter_map(|page| {
+                let start = page.find("<article>")?;
+                let end = page.rfind("</article>")? + "</article>".len();
+                Some(&page[start..end])
+            })
+            .collect::<Vec<_>>()
+            .join("\n<hr>\n");
+        page(
+            "Issues",
+            &format!("<main class=\"container\">\n{}\n</main>\n", articles),
+        )
+    }
+
+    fn write_index(&self, entries: &[IndexEntry]) -> Option<String> {
+        let rows = entries
+            .iter()
+            .map(|entry| {
+                let issue = entry.issue;
+                format!(
+                    r#"            <tr data-state="{}" data-labels="{}" data-text="{}">
+                <td data-sort="{}">#{}</td>
+                <td>{}<br>{}</td>
+                <td class="state state-{}">{}</td>
+                <td>{}</td>
+                <td data-sort="{}">{}</td>
+                <td data-sort="{}">{}</td>
+            </tr>
+"#,
+                    escape_html(issue.state()),
+                    escape_html(&issue.labels().join("\n")),
+                    escape_html(
+                        &format!("{} {} {}", issue.number(), issue.title(), issue.user())
+                            .to_lowercase()
+                    ),
+                    issue.number(),
+                    issue.number(),
+                    match &entry.path {
+                        Some(path) => format!(
+                            "<a href=\"{}\">{}</a>",
+                            escape_html(path),
+                            escape_html(issue.title())
+                        ),
+                        None => escape_html(issue.title()),
+                    },
+                    label_badges(issue.labels()),
+                    escape_html(issue.state()),
+                    escape_html(issue.state()),
+                    escape_html(issue.user()),
+                    issue.created_at().to_rfc3339(),
+                    escape_html(&self.dates.render(issue.created_at())),
+                    issue.updated_at().to_rfc3339(),
+                    escape_html(&self.dates.render(issue.updated_at())),
+                )
+            })
+            .collect::<String>();
+
+        Some(page(
+            "Issues",
+            &format!(
+                r#"<header>
+    <div class="container"><h1>Issues</h1></div>
+</header>
+<main class="container">
+    <div class="filters">
+        <input id="search" type="search" placeholder="Filter by number, title or author">
+        <select id="state">
+            <option value="">All states</option>
+            <option value="open">Open</option>
+            <option value="closed">Closed</option>
+        </select>
+    </div>
+    <p class="meta">{} issues &middot; click a label to filter by it, click a column header to sort</p>
+    <table id="issues">
+        <thead>
+            <tr><th>#</th><th>Title</th><th>State</th><th>Author</th><th>Created</th><th>Updated</th></tr>
+        </thead>
+        <tbody>
+{}        </tbody>
+    </table>
+</main>
+<script>{}</script>
+"#,
+                entries.len(),
+                rows,
+                INDEX_SCRIPT
+            ),
+        ))
+    }
+}
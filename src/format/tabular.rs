@@ -0,0 +1,91 @@
+use super::{FormatWriter, IndexEntry};
+use crate::budget::Elision;
+use crate::dates::DateFormat;
+use crate::github::{CommentData, IssueData};
+
+/// Writes all issues into a single spreadsheet-friendly file, one row per issue.
+pub struct TabularWriter {
+    pub delimiter: u8,
+    pub dates: DateFormat,
+}
+
+const HEADER: [&str; 14] = [
+    "number",
+    "title",
+    "state",
+    "labels",
+    "assignees",
+    "author",
+    "created_at",
+    "updated_at",
+    "closed_at",
+    "comments",
+    "url",
+    "state_reason",
+    "milestone",
+    "closed_by",
+];
+
+impl TabularWriter {
+    fn write_rows<'a>(&self, issues: impl Iterator<Item = &'a IssueData>, header: bool) -> String {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(Vec::new());
+
+        // Writing to a Vec can't fail, so errors are ignored rather than threaded through
+        if header {
+            let _ = writer.write_record(HEADER);
+        }
+        for issue in issues {
+            let _ = writer.write_record([
+                issue.number().to_string().as_str(),
+                issue.title(),
+                issue.state(),
+                &issue.labels().join(", "),
+                &issue.assignees().join(", "),
+                issue.user(),
+                &self.dates.render(issue.created_at()),
+                &self.dates.render(issue.updated_at()),
+                &issue
+                    .closed_at()
+                    .map(|dt| self.dates.render(dt))
+                    .unwrap_or_default(),
+                &issue.comment_count().to_string(),
+                issue.html_url(),
+                issue.state_reason().unwrap_or_default(),
+                issue.milestone().unwrap_or_default(),
+                issue.closed_by().unwrap_or_default(),
+            ]);
+        }
+
+        writer
+            .into_inner()
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl FormatWriter for TabularWriter {
+    fn write_issue(
+        &self,
+        issue: &IssueData,
+        _comments: &[CommentData],
+        _elision: Option<&Elision>,
+    ) -> String {
+        self.write_rows(std::iter::once(issue), false)
+    }
+
+    fn combine(&self, issues: &[&str]) -> String {
+        let mut content = self.write_rows(std::iter::empty(), true);
+        content.push_str(&issues.concat());
+        content
+    }
+
+    fn write_index(&self, entries: &[IndexEntry]) -> Option<String> {
+        Some(self.write_rows(entries.iter().map(|e| e.issue), true))
+    }
+
+    fn writes_issue_files(&self) -> bool {
+        false
+    }
+}
@@ -1,12 +1,19 @@
+use crate::auth::Credentials;
 use crate::error::GhError;
-use console::{style, Term};
-use octocrab::models::issues::Issue;
+use chrono::{DateTime, Utc};
+use console::style;
+use jsonwebtoken::EncodingKey;
+use octocrab::models::issues::{Comment, Issue};
+use octocrab::models::{AppId, Author, InstallationId, RateLimit};
 use octocrab::params;
 use octocrab::params::issues::Sort;
 use octocrab::params::Direction;
-use serde::Serialize;
+use octocrab::Page;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct IssueData {
     number: u64,
@@ -14,11 +21,89 @@ pub struct IssueData {
     state: String,
     body: Option<String>,
     labels: Vec<String>,
-    created_at: String,
-    updated_at: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    closed_at: Option<DateTime<Utc>>,
     assignees: Vec<String>,
     user: String,
+    html_url: String,
     comments_url: Option<String>,
+    comment_count: u32,
+    state_reason: Option<String>,
+    closed_by: Option<String>,
+    milestone: Option<String>,
+    locked: bool,
+    active_lock_reason: Option<String>,
+    author_association: String,
+    #[serde(default)]
+    pull_request: bool,
+    #[serde(default)]
+    merged_at: Option<DateTime<Utc>>,
+}
+
+/// An issue as returned by the REST API, including fields octocrab's model drops.
+#[derive(Deserialize)]
+struct IssueRecord {
+    #[serde(flatten)]
+    issue: Issue,
+    /// Read here rather than through octocrab's enum, which fails on reasons it
+    /// doesn't know, such as `duplicate`
+    state_reason: Option<String>,
+    closed_by: Option<Author>,
+    /// Set when the "issue" is a pull request
+    pull_request: Option<PullRequestRecord>,
+}
+
+#[derive(Deserialize)]
+struct PullRequestRecord {
+    merged_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct ListIssuesParams {
+    per_page: u8,
+    state: params::State,
+    direction: Direction,
+    sort: Sort,
+    /// Only issues updated at or after this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since: Option<DateTime<Utc>>,
+    /// Milestone number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    milestone: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct CommitRecord {
+    commit: CommitDetail,
+}
+
+#[derive(Deserialize)]
+struct CommitDetail {
+    committer: CommitSignature,
+}
+
+#[derive(Deserialize)]
+struct CommitSignature {
+    date: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct MilestoneRecord {
+    number: u64,
+    title: String,
+}
+
+impl From<IssueRecord> for IssueData {
+    fn from(record: IssueRecord) -> Self {
+        IssueData {
+            state_reason: record.state_reason,
+            closed_by: record.closed_by.map(|a| a.login),
+            pull_request: record.pull_request.is_some(),
+            merged_at: record.pull_request.and_then(|pr| pr.merged_at),
+            ..IssueData::from(record.issue)
+        }
+    }
 }
 
 impl From<Issue> for IssueData {
@@ -34,11 +119,22 @@ impl From<Issue> for IssueData {
             .to_string(),
             body: issue.body,
             labels: issue.labels.into_iter().map(|l| l.name).collect(),
-            created_at: issue.created_at.to_string(),
-            updated_at: issue.updated_at.to_string(),
+            created_at: issue.created_at,
+            updated_at: issue.updated_at,
+            closed_at: issue.closed_at,
             assignees: issue.assignees.into_iter().map(|a| a.login).collect(),
             user: issue.user.login,
+            html_url: issue.html_url.to_string(),
             comments_url: Some(issue.comments_url.to_string()),
+            comment_count: issue.comments,
+            state_reason: None,
+            closed_by: None,
+            milestone: issue.milestone.map(|m| m.title),
+            locked: issue.locked,
+            active_lock_reason: issue.active_lock_reason,
+            author_association: issue.author_association,
+            pull_request: issue.pull_request.is_some(),
+            merged_at: None,
         }
     }
 }
@@ -61,14 +157,22 @@ impl IssueData {
         self.body.as_deref()
     }
 
-    pub fn created_at(&self) -> &str {
+    pub fn set_body(&mut self, body: Option<String>) {
+        self.body = body;
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
         &self.created_at
     }
 
-    pub fn updated_at(&self) -> &str {
+    pub fn updated_at(&self) -> &DateTime<Utc> {
         &self.updated_at
     }
 
+    pub fn closed_at(&self) -> Option<&DateTime<Utc>> {
+        self.closed_at.as_ref()
+    }
+
     pub fn labels(&self) -> &[String] {
         &self.labels
     }
@@ -80,46 +184,310 @@ impl IssueData {
     pub fn user(&self) -> &str {
         &self.user
     }
+
+    pub fn html_url(&self) -> &str {
+        &self.html_url
+    }
+
+    pub fn comment_count(&self) -> u32 {
+        self.comment_count
+    }
+
+    /// Why a closed issue was closed: `completed`, `not_planned` or `duplicate` (or
+    /// `reopened`).
+    pub fn state_reason(&self) -> Option<&str> {
+        self.state_reason.as_deref()
+    }
+
+    /// Only reported when the issue is fetched on its own (`--issues`), not when listing.
+    pub fn closed_by(&self) -> Option<&str> {
+        self.closed_by.as_deref()
+    }
+
+    pub fn milestone(&self) -> Option<&str> {
+        self.milestone.as_deref()
+    }
+
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn active_lock_reason(&self) -> Option<&str> {
+        self.active_lock_reason.as_deref()
+    }
+
+    pub fn author_association(&self) -> &str {
+        &self.author_association
+    }
+
+    /// Whether this is a pull request; only listed with [`GitHubClient::fetch_closed`].
+    pub fn is_pull_request(&self) -> bool {
+        self.pull_request
+    }
+
+    pub fn merged_at(&self) -> Option<&DateTime<Utc>> {
+        self.merged_at.as_ref()
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CommentData {
+    pub id: u64,
+    pub html_url: String,
     pub user: String,
     pub body: String,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// The commenter's relationship to the repository, e.g. `OWNER`, `MEMBER`, `CONTRIBUTOR`
+    pub author_association: String,
+    pub edited: bool,
+    pub is_bot: bool,
+    /// Only known when minimized status was requested from the GraphQL API
+    pub minimized: bool,
+    pub minimized_reason: Option<String>,
+}
+
+/// A comment as returned by the REST API, including fields octocrab's model drops.
+#[derive(Deserialize)]
+struct CommentRecord {
+    #[serde(flatten)]
+    comment: Comment,
+    #[serde(default)]
+    author_association: String,
+}
+
+impl From<CommentRecord> for CommentData {
+    fn from(record: CommentRecord) -> Self {
+        let comment = record.comment;
+        CommentData {
+            id: comment.id.0,
+            html_url: comment.html_url.to_string(),
+            is_bot: comment.user.r#type == "Bot" || comment.user.login.ends_with("[bot]"),
+            user: comment.user.login,
+            body: comment.body.unwrap_or_default(),
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+            author_association: record.author_association,
+            edited: comment
+                .updated_at
+                .is_some_and(|updated| updated > comment.created_at),
+            minimized: false,
+            minimized_reason: None,
+        }
+    }
+}
+
+const MINIMIZED_COMMENTS_QUERY: &str = r#"
+query($owner: String!, $repo: String!, $number: Int!, $cursor: String) {
+  repository(owner: $owner, name: $repo) {
+    issue(number: $number) {
+      comments(first: 100, after: $cursor) {
+        nodes { databaseId isMinimized minimizedReason }
+        pageInfo { hasNextPage endCursor }
+      }
+    }
+  }
+}
+"#;
+
+#[derive(Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+    /// e.g. `RATE_LIMITED`, `NOT_FOUND`, `FORBIDDEN`
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MinimizedData {
+    repository: Option<MinimizedRepository>,
+}
+
+#[derive(Deserialize)]
+struct MinimizedRepository {
+    issue: Option<MinimizedIssue>,
+}
+
+#[derive(Deserialize)]
+struct MinimizedIssue {
+    comments: MinimizedConnection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MinimizedConnection {
+    nodes: Vec<MinimizedComment>,
+    page_info: PageInfo,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MinimizedComment {
+    database_id: Option<u64>,
+    is_minimized: bool,
+    minimized_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+/// A raw API response, for diagnostics that need status codes and headers.
+pub struct ApiProbe {
+    pub status: u16,
+    headers: hyper::HeaderMap,
+    /// The JSON body, or `Null` if it wasn't JSON
+    pub body: serde_json::Value,
+}
+
+impl ApiProbe {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// The `message` GitHub includes in error responses.
+    pub fn message(&self) -> Option<&str> {
+        self.body.get("message")?.as_str()
+    }
+}
+
+/// An issue that couldn't be fetched while the rest of the run carried on.
+#[derive(Debug)]
+pub struct IssueFailure {
+    pub number: u64,
+    pub error: GhError,
+}
+
+/// What became of an issue that's no longer in a listing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum MissingIssue {
+    /// Still in the repository, but no longer matching the `--state` filter
+    StateChanged {
+        state: String,
+    },
+    Transferred {
+        url: String,
+    },
+    ConvertedToDiscussion {
+        url: String,
+    },
+    Deleted,
+    /// Not found: deleted, or no longer readable with these credentials
+    Unavailable,
+}
+
+impl std::fmt::Display for MissingIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MissingIssue::StateChanged { state } => write!(f, "now {}", state),
+            MissingIssue::Transferred { url } => write!(f, "transferred to {}", url),
+            MissingIssue::ConvertedToDiscussion { url } => {
+                write!(f, "converted to discussion {}", url)
+            }
+            MissingIssue::Deleted => write!(f, "deleted"),
+            MissingIssue::Unavailable => write!(f, "not found"),
+        }
+    }
 }
 
 #[allow(dead_code)]
 pub struct GitHubClient {
     octocrab: octocrab::Octocrab,
+    /// API requests made so far, for run reports
+    requests: AtomicUsize,
 }
 
 #[allow(dead_code)]
 impl GitHubClient {
-    pub async fn new() -> std::result::Result<Self, GhError> {
-        let token = std::env::var("GITHUB_TOKEN").ok();
-        let octocrab = if let Some(token) = token {
-            octocrab::OctocrabBuilder::new()
-                .personal_token(token)
+    /// Builds a client for the resolved credentials. A GitHub App without an
+    /// installation ID needs `repo` to look up its installation.
+    pub async fn new(
+        credentials: &Credentials,
+        repo: Option<(&str, &str)>,
+    ) -> std::result::Result<Self, GhError> {
+        let mut requests = 0;
+        let octocrab = match credentials {
+            Credentials::Token { token, .. } => octocrab::OctocrabBuilder::new()
+                .personal_token(token.clone())
                 .build()
-                .map_err(|e| GhError::AuthError(e.to_string()))?
-        } else {
-            let term = Term::stderr();
-            term.write_line(&format!(
-                "{} Running without GITHUB_TOKEN. API rate limits will be restricted.",
-                style("Note:").blue().bold()
-            ))?;
-            term.write_line(&format!(
-                "{} To increase rate limits, you can set the GITHUB_TOKEN environment variable.",
-                style("Tip:").cyan().bold()
-            ))?;
-            octocrab::OctocrabBuilder::new()
+                .map_err(|e| GhError::AuthError(e.to_string()))?,
+            Credentials::App(app) => {
+                let key = EncodingKey::from_rsa_pem(app.private_key.as_bytes()).map_err(|e| {
+                    GhError::AuthError(format!("Invalid GitHub App private key: {}", e))
+                })?;
+                let app_client = octocrab::OctocrabBuilder::new()
+                    .app(AppId(app.app_id), key)
+                    .build()
+                    .map_err(|e| GhError::AuthError(e.to_string()))?;
+                let installation_id = match (app.installation_id, repo) {
+                    (Some(id), _) => InstallationId(id),
+                    (None, Some((owner, repo))) => {
+                        requests += 1;
+                        app_client
+                            .apps()
+                            .get_repository_installation(owner, repo)
+                            .await
+                            .map_err(|e| {
+                                GhError::AuthError(format!(
+                                    "GitHub App {} is not installed on {}/{}: {}",
+                                    app.app_id, owner, repo, e
+                                ))
+                            })?
+                            .id
+                    }
+                    (None, None) => {
+                        return Err(GhError::AuthError(
+                            "--app-installation-id is required without a repository".to_string(),
+                        ))
+                    }
+                };
+                app_client.installation(installation_id)
+            }
+            Credentials::Anonymous => octocrab::OctocrabBuilder::new()
                 .build()
-                .map_err(|e| GhError::AuthError(e.to_string()))?
+                .map_err(|e| GhError::AuthError(e.to_string()))?,
         };
 
-        Ok(Self { octocrab })
+        Ok(Self {
+            octocrab,
+            requests: AtomicUsize::new(requests),
+        })
+    }
+
+    /// API requests made by this client so far, not counting rate limit checks (which
+    /// don't count against the limit either).
+    pub fn requests_made(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    fn count_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    async fn next_page<T: DeserializeOwned>(
+        &self,
+        page: &Page<T>,
+    ) -> octocrab::Result<Option<Page<T>>> {
+        if page.next.is_none() {
+            return Ok(None);
+        }
+        self.count_request();
+        self.octocrab.get_page(&page.next).await
     }
 
     pub async fn fetch_issues(
@@ -129,112 +497,399 @@ impl GitHubClient {
         state: Option<&str>,
         numbers: Option<&[u32]>,
         batch_size: usize,
-    ) -> std::result::Result<Vec<IssueData>, GhError> {
+    ) -> std::result::Result<(Vec<IssueData>, Vec<IssueFailure>), GhError> {
         let mut issues = Vec::new();
+        let mut failures = Vec::new();
 
         if let Some(nums) = numbers {
             for chunk in nums.chunks(batch_size) {
                 for &number in chunk {
-                    match self.octocrab.issues(owner, repo).get(number as u64).await {
-                        Ok(issue) => {
-                            println!("Successfully fetched issue #{}", number);
-                            issues.push(IssueData::from(issue));
-                        }
+                    let route = format!("/repos/{}/{}/issues/{}", owner, repo, number);
+                    self.count_request();
+                    match self.octocrab.get::<IssueRecord, _, ()>(route, None).await {
+                        Ok(record) => issues.push(IssueData::from(record)),
                         Err(e) => {
-                            eprintln!("Warning: Failed to fetch issue #{}: {} (This issue might be private or deleted)", number, e);
-                            continue;
+                            let context = format!("issue #{} in {}/{}", number, owner, repo);
+                            let error = self.api_error(e, &context).await;
+                            if !error.is_issue_specific() {
+                                return Err(error);
+                            }
+                            // Other issues may still be readable, so only this one is skipped
+                            failures.push(IssueFailure {
+                                number: number as u64,
+                                error,
+                            });
                         }
                     }
                 }
                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
             }
         } else {
-            let issues_handler = self.octocrab.issues(owner, repo);
             let state_param = match state {
                 Some("open") => params::State::Open,
                 Some("closed") => params::State::Closed,
                 _ => params::State::All,
             };
+            let list_params = ListIssuesParams {
+                per_page: 100,
+                state: state_param,
+                direction: Direction::Descending,
+                sort: Sort::Created,
+                since: None,
+                milestone: None,
+            };
+            issues = self.list_issues(owner, repo, &list_params, false).await?;
+        }
 
-            let mut page = match issues_handler
-                .list()
-                .per_page(100)
-                .state(state_param)
-                .direction(Direction::Descending)
-                .sort(Sort::Created)
-                .send()
-                .await
-            {
-                Ok(page) => page,
-                Err(octocrab::Error::GitHub { source, .. }) if source.message == "Not Found" => {
-                    // When there are no issues, GitHub returns 404
-                    return Ok(Vec::new());
+        Ok((issues, failures))
+    }
+
+    /// Closed issues, and with `include_pull_requests` pull requests, updated since
+    /// `since` and in the milestone with number `milestone`, if given. Which of them
+    /// were closed in a range is left to the caller, as GitHub only filters by update.
+    pub async fn fetch_closed(
+        &self,
+        owner: &str,
+        repo: &str,
+        since: Option<DateTime<Utc>>,
+        milestone: Option<u64>,
+        include_pull_requests: bool,
+    ) -> std::result::Result<Vec<IssueData>, GhError> {
+        let list_params = ListIssuesParams {
+            per_page: 100,
+            state: params::State::Closed,
+            direction: Direction::Descending,
+            sort: Sort::Updated,
+            since,
+            milestone,
+        };
+        self.list_issues(owner, repo, &list_params, include_pull_requests)
+            .await
+    }
+
+    async fn list_issues(
+        &self,
+        owner: &str,
+        repo: &str,
+        list_params: &ListIssuesParams,
+        include_pull_requests: bool,
+    ) -> std::result::Result<Vec<IssueData>, GhError> {
+        let route = format!("/repos/{}/{}/issues", owner, repo);
+        let context = format!("{}/{}", owner, repo);
+        let mut issues = Vec::new();
+
+        self.count_request();
+        let mut page = match self
+            .octocrab
+            .get::<Page<IssueRecord>, _, _>(route, Some(list_params))
+            .await
+        {
+            Ok(page) => page,
+            // A repository without issues answers with an empty page, so a 404 means
+            // the repository doesn't exist or can't be read with these credentials
+            Err(e) => return Err(self.api_error(e, &context).await),
+        };
+
+        loop {
+            for record in page.items.drain(..) {
+                if include_pull_requests || record.pull_request.is_none() {
+                    issues.push(IssueData::from(record));
                 }
-                Err(e) => return Err(GhError::ApiError(e.to_string())),
+            }
+
+            page = match self.next_page(&page).await {
+                Ok(Some(next_page)) => next_page,
+                Ok(None) => break,
+                Err(e) => return Err(self.api_error(e, &context).await),
             };
+        }
+        Ok(issues)
+    }
 
-            loop {
-                for issue in page.items {
-                    if issue.pull_request.is_none() {
-                        issues.push(IssueData::from(issue));
-                    }
-                }
+    /// When a tag, branch or commit was committed.
+    pub async fn ref_date(
+        &self,
+        owner: &str,
+        repo: &str,
+        reference: &str,
+    ) -> std::result::Result<DateTime<Utc>, GhError> {
+        let route = format!("/repos/{}/{}/commits/{}", owner, repo, reference);
+        let context = format!("ref {} in {}/{}", reference, owner, repo);
+        self.count_request();
+        match self.octocrab.get::<CommitRecord, _, ()>(route, None).await {
+            Ok(record) => Ok(record.commit.committer.date),
+            Err(e) => Err(self.api_error(e, &context).await),
+        }
+    }
 
-                page = match self.octocrab.get_page(&page.next).await {
-                    Ok(Some(next_page)) => next_page,
-                    Ok(None) => break,
-                    Err(e) => return Err(GhError::ApiError(e.to_string())),
-                };
+    /// The number of the milestone with this title, open or closed.
+    pub async fn milestone_number(
+        &self,
+        owner: &str,
+        repo: &str,
+        title: &str,
+    ) -> std::result::Result<u64, GhError> {
+        let route = format!(
+            "/repos/{}/{}/milestones?state=all&per_page=100",
+            owner, repo
+        );
+        let context = format!("milestones of {}/{}", owner, repo);
+        self.count_request();
+        let mut page = match self
+            .octocrab
+            .get::<Page<MilestoneRecord>, _, ()>(route, None)
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => return Err(self.api_error(e, &context).await),
+        };
+
+        loop {
+            if let Some(milestone) = page.items.iter().find(|m| m.title == title) {
+                return Ok(milestone.number);
             }
+            page = match self.next_page(&page).await {
+                Ok(Some(next_page)) => next_page,
+                Ok(None) => break,
+                Err(e) => return Err(self.api_error(e, &context).await),
+            };
         }
-
-        Ok(issues)
+        Err(GhError::NotFound(format!(
+            "milestone \"{}\" in {}/{}",
+            title, owner, repo
+        )))
     }
 
+    /// Fetches an issue's comments. With `with_minimized`, also asks the GraphQL API
+    /// which comments are hidden, which costs an extra request per issue and needs a token.
     pub async fn fetch_comments(
         &self,
         owner: &str,
         repo: &str,
         issue_number: u64,
+        with_minimized: bool,
     ) -> std::result::Result<Vec<CommentData>, GhError> {
         let mut comments = Vec::new();
-        let mut page = self
+        let route = format!(
+            "/repos/{}/{}/issues/{}/comments?per_page=100",
+            owner, repo, issue_number
+        );
+        let context = format!("comments of issue #{} in {}/{}", issue_number, owner, repo);
+        self.count_request();
+        let mut page = match self
             .octocrab
-            .issues(owner, repo)
-            .list_comments(issue_number)
-            .send()
+            .get::<Page<CommentRecord>, _, ()>(route, None)
             .await
-            .map_err(|e| GhError::ApiError(format!("Failed to fetch comments: {}", e)))?;
+        {
+            Ok(page) => page,
+            Err(e) => return Err(self.api_error(e, &context).await),
+        };
 
         loop {
-            for comment in page.items {
-                comments.push(CommentData {
-                    user: comment.user.login,
-                    body: comment.body.unwrap_or_default(),
-                    created_at: comment.created_at.to_string(),
-                    updated_at: comment
-                        .updated_at
-                        .map_or_else(|| "N/A".to_string(), |dt| dt.to_string()),
-                });
+            for record in page.items.drain(..) {
+                comments.push(CommentData::from(record));
             }
 
-            page = match self.octocrab.get_page(&page.next).await {
+            page = match self.next_page(&page).await {
                 Ok(Some(next_page)) => next_page,
                 Ok(None) => break,
-                Err(e) => return Err(GhError::ApiError(e.to_string())),
+                Err(e) => return Err(self.api_error(e, &context).await),
             };
         }
 
+        if with_minimized && !comments.is_empty() {
+            for minimized in self
+                .fetch_minimized_comments(owner, repo, issue_number)
+                .await?
+            {
+                if let Some(comment) = comments
+                    .iter_mut()
+                    .find(|c| Some(c.id) == minimized.database_id)
+                {
+                    comment.minimized = minimized.is_minimized;
+                    comment.minimized_reason = minimized.minimized_reason;
+                }
+            }
+        }
+
         Ok(comments)
     }
 
-    pub async fn get_rate_limit_info(&self) -> std::result::Result<String, GhError> {
-        let rate_limit = self
+    async fn fetch_minimized_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> std::result::Result<Vec<MinimizedComment>, GhError> {
+        let mut minimized = Vec::new();
+        let mut cursor: Option<String> = None;
+        let context = format!(
+            "comment visibility of issue #{} in {}/{}",
+            issue_number, owner, repo
+        );
+
+        loop {
+            self.count_request();
+            let response = self
+                .octocrab
+                .graphql::<GraphQlResponse<MinimizedData>>(&serde_json::json!({
+                    "query": MINIMIZED_COMMENTS_QUERY,
+                    "variables": {
+                        "owner": owner,
+                        "repo": repo,
+                        "number": issue_number,
+                        "cursor": cursor,
+                    },
+                }))
+                .await;
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => return Err(self.api_error(e, &context).await),
+            };
+
+            if let Some(error) = response.errors.first() {
+                let message = format!("{}: {}", context, error.message);
+                return Err(match error.kind.as_deref() {
+                    Some("RATE_LIMITED") => GhError::RateLimited {
+                        reset: self.rate_limit_reset(true).await,
+                    },
+                    Some("NOT_FOUND") => GhError::NotFound(message),
+                    Some("FORBIDDEN") => GhError::Forbidden(message),
+                    _ => GhError::ApiError(message),
+                });
+            }
+            let Some(connection) = response
+                .data
+                .and_then(|d| d.repository)
+                .and_then(|r| r.issue)
+                .map(|i| i.comments)
+            else {
+                break;
+            };
+
+            minimized.extend(connection.nodes);
+            if !connection.page_info.has_next_page {
+                break;
+            }
+            cursor = connection.page_info.end_cursor;
+        }
+
+        Ok(minimized)
+    }
+
+    pub async fn download_asset(&self, url: &str) -> std::result::Result<Vec<u8>, GhError> {
+        self.count_request();
+        let response = match self.octocrab._get(url).await {
+            Ok(response) => response,
+            Err(e) => return Err(self.api_error(e, url).await),
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(status_error(status.as_u16(), response.headers(), url));
+        }
+
+        let bytes = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| GhError::Network(format!("Failed to download {}: {}", url, e)))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Classifies an octocrab error, looking up when the rate limit resets if that's
+    /// what was hit.
+    async fn api_error(&self, error: octocrab::Error, context: &str) -> GhError {
+        match classify(error, context) {
+            GhError::RateLimited { reset: None } => GhError::RateLimited {
+                reset: self.rate_limit_reset(false).await,
+            },
+            error => error,
+        }
+    }
+
+    /// When the core (or GraphQL) rate limit resets. Checking doesn't count against it.
+    async fn rate_limit_reset(&self, graphql: bool) -> Option<DateTime<Utc>> {
+        let rate_limit = self.octocrab.ratelimit().get().await.ok()?;
+        let rate = match rate_limit.resources.graphql {
+            Some(rate) if graphql => rate,
+            _ => rate_limit.resources.core,
+        };
+        DateTime::from_timestamp(rate.reset as i64, 0)
+    }
+
+    /// GETs an API route without treating error statuses as failures, so callers can
+    /// inspect them.
+    pub async fn probe(&self, route: &str) -> std::result::Result<ApiProbe, GhError> {
+        self.count_request();
+        let response = self
             .octocrab
-            .ratelimit()
-            .get()
+            ._get(route)
+            .await
+            .map_err(|e| classify(e, route))?;
+
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let bytes = hyper::body::to_bytes(response.into_body())
             .await
-            .map_err(|e| GhError::RateLimitError(e.to_string()))?;
+            .map_err(|e| GhError::Network(format!("Failed to read {}: {}", route, e)))?;
+
+        Ok(ApiProbe {
+            status,
+            headers,
+            body: serde_json::from_slice(&bytes).unwrap_or_default(),
+        })
+    }
+
+    /// Looks up an issue that's missing from a listing to find out why. Transferred
+    /// issues redirect to their new repository, deleted ones answer 410 Gone.
+    pub async fn locate_missing_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> std::result::Result<MissingIssue, GhError> {
+        let route = format!("/repos/{}/{}/issues/{}", owner, repo, number);
+        let response = self.probe(&route).await?;
+
+        match response.status {
+            404 => return Ok(MissingIssue::Unavailable),
+            410 => return Ok(MissingIssue::Deleted),
+            // Including 403, so a rate limit stops the run instead of pruning on a wrong reason
+            _ if !response.is_success() => {
+                let context = format!("issue #{} in {}/{}", number, owner, repo);
+                return Err(status_error(response.status, &response.headers, &context));
+            }
+            _ => {}
+        }
+
+        let url = response.body["html_url"].as_str().unwrap_or_default();
+        let here = format!("https://github.com/{}/{}/issues/", owner, repo);
+        Ok(if url.contains("/discussions/") {
+            MissingIssue::ConvertedToDiscussion {
+                url: url.to_string(),
+            }
+        } else if !url.to_lowercase().starts_with(&here.to_lowercase()) {
+            MissingIssue::Transferred {
+                url: url.to_string(),
+            }
+        } else {
+            MissingIssue::StateChanged {
+                state: response.body["state"]
+                    .as_str()
+                    .unwrap_or("unknown")
+                    .to_string(),
+            }
+        })
+    }
+
+    pub async fn rate_limits(&self) -> std::result::Result<RateLimit, GhError> {
+        match self.octocrab.ratelimit().get().await {
+            Ok(rate_limit) => Ok(rate_limit),
+            Err(e) => Err(classify(e, "rate limit")),
+        }
+    }
+
+    pub async fn get_rate_limit_info(&self) -> std::result::Result<String, GhError> {
+        let rate_limit = self.rate_limits().await?;
 
         Ok(format!(
             "{} {}/{} remaining. Reset at: {}",
@@ -252,3 +907,232 @@ impl GitHubClient {
         ))
     }
 }
+
+/// GitHub suggests waiting at least a minute after a secondary rate limit when it
+/// doesn't say how long.
+const DEFAULT_RETRY_AFTER: u64 = 60;
+
+/// Maps an octocrab error to a specific `GhError`. octocrab doesn't keep the HTTP
+/// status of API errors, so GitHub's error messages are matched instead.
+fn classify(error: octocrab::Error, context: &str) -> GhError {
+    match error {
+        octocrab::Error::GitHub { source, .. } => message_error(&source.message, context),
+        octocrab::Error::Service { source, .. } => {
+            GhError::Network(format!("{}: {}", context, source))
+        }
+        octocrab::Error::Hyper { source, .. } => {
+            GhError::Network(format!("{}: {}", context, source))
+        }
+        octocrab::Error::Http { source, .. } => {
+            GhError::Network(format!("{}: {}", context, source))
+        }
+        error => GhError::ApiError(format!("{}: {}", context, error)),
+    }
+}
+
+fn message_error(message: &str, context: &str) -> GhError {
+    let lower = message.to_lowercase();
+    let detailed = format!("{}: {}", context, message);
+
+    if lower.contains("secondary rate limit") || lower.contains("abuse detection") {
+        GhError::SecondaryLimit {
+            retry_after: DEFAULT_RETRY_AFTER,
+        }
+    } else if lower.contains("rate limit exceeded") {
+        GhError::RateLimited { reset: None }
+    } else if lower.contains("issues are disabled") {
+        GhError::IssuesDisabled(context.to_string())
+    } else if lower == "not found" || lower.contains("was deleted") {
+        GhError::NotFound(context.to_string())
+    } else if lower.contains("bad credentials") || lower.contains("requires authentication") {
+        GhError::AuthError(detailed)
+    } else if lower.contains("resource not accessible")
+        || lower.contains("forbidden")
+        || lower.contains("saml")
+        || lower.contains("access restrictions")
+        || lower.contains("access blocked")
+    {
+        GhError::Forbidden(detailed)
+    } else {
+        GhError::ApiError(detailed)
+    }
+}
+
+/// Maps an error status of a raw response, where the rate limit headers are available.
+fn status_error(status: u16, headers: &hyper::HeaderMap, context: &str) -> GhError {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    match status {
+        401 => GhError::AuthError(format!("{}: HTTP 401", context)),
+        404 | 410 => GhError::NotFound(context.to_string()),
+        403 | 429 if header("x-ratelimit-remaining") == Some("0") => GhError::RateLimited {
+            reset: header("x-ratelimit-reset")
+                .and_then(|reset| reset.parse().ok())
+                .and_then(|reset| DateTime::from_timestamp(reset, 0)),
+        },
+        403 | 429 if header("retry-after").is_some() || status == 429 => GhError::SecondaryLimit {
+            retry_after: header("retry-after")
+                .and_then(|retry_after| retry_after.parse().ok())
+                .unwrap_or(DEFAULT_RETRY_AFTER),
+        },
+        403 => GhError::Forbidden(format!("{}: HTTP 403", context)),
+        _ => GhError::ApiError(format!("{}: HTTP {}", context, status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user(login: &str) -> serde_json::Value {
+        let url = format!("https://api.github.com/users/{}", login);
+        json!({
+            "login": login, "id": 1, "node_id": "U_1",
+            "avatar_url": url, "gravatar_id": "", "url": url, "html_url": url,
+            "followers_url": url, "following_url": url, "gists_url": url,
+            "starred_url": url, "subscriptions_url": url, "organizations_url": url,
+            "repos_url": url, "events_url": url, "received_events_url": url,
+            "type": "User", "site_admin": false, "patch_url": null
+        })
+    }
+
+    fn issue_record(state_reason: Option<&str>) -> serde_json::Value {
+        let url = "https://api.github.com/repos/owner/repo/issues/7";
+        json!({
+            "id": 7, "node_id": "I_7", "url": url, "repository_url": url,
+            "labels_url": url, "comments_url": url, "events_url": url,
+            "html_url": "https://github.com/owner/repo/issues/7",
+            "number": 7, "state": "closed", "state_reason": state_reason,
+            "title": "Crash", "body": null, "user": user("octocat"), "labels": [],
+            "assignees": [], "author_association": "NONE", "locked": false,
+            "comments": 0, "closed_at": "2024-01-02T00:00:00Z",
+            "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-02T00:00:00Z",
+            "closed_by": user("hubot")
+        })
+    }
+
+    #[test]
+    fn reads_state_reasons_octocrab_does_not_know() {
+        for reason in ["completed", "not_planned", "duplicate"] {
+            let record: IssueRecord = serde_json::from_value(issue_record(Some(reason))).unwrap();
+            let issue = IssueData::from(record);
+            assert_eq!(issue.state_reason(), Some(reason));
+            assert_eq!(issue.closed_by(), Some("hubot"));
+        }
+        let record: IssueRecord = serde_json::from_value(issue_record(None)).unwrap();
+        assert_eq!(IssueData::from(record).state_reason(), None);
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> hyper::HeaderMap {
+        let mut headers = hyper::HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn classifies_error_messages() {
+        let error = |message| message_error(message, "owner/repo");
+        assert!(matches!(
+            error("You have exceeded a secondary rate limit"),
+            GhError::SecondaryLimit {
+                retry_after: DEFAULT_RETRY_AFTER
+            }
+        ));
+        assert!(matches!(
+            error("You have triggered an abuse detection mechanism"),
+            GhError::SecondaryLimit { .. }
+        ));
+        assert!(matches!(
+            error("API rate limit exceeded for 1.2.3.4"),
+            GhError::RateLimited { reset: None }
+        ));
+        assert!(matches!(
+            error("Issues are disabled for this repo"),
+            GhError::IssuesDisabled(_)
+        ));
+        assert!(matches!(error("Not Found"), GhError::NotFound(_)));
+        assert!(matches!(
+            error("This issue was deleted"),
+            GhError::NotFound(_)
+        ));
+        assert!(matches!(error("Bad credentials"), GhError::AuthError(_)));
+        assert!(matches!(
+            error("Requires authentication"),
+            GhError::AuthError(_)
+        ));
+        assert!(matches!(
+            error("Resource not accessible by integration"),
+            GhError::Forbidden(_)
+        ));
+        assert!(matches!(
+            error("Resource protected by organization SAML enforcement"),
+            GhError::Forbidden(_)
+        ));
+        assert!(matches!(error("Validation Failed"), GhError::ApiError(_)));
+    }
+
+    #[test]
+    fn only_an_exact_not_found_message_means_not_found() {
+        assert!(matches!(
+            message_error("Branch not found in the repository", "ref"),
+            GhError::ApiError(_)
+        ));
+    }
+
+    #[test]
+    fn classifies_statuses() {
+        let error = |status, pairs: &[(&'static str, &str)]| {
+            status_error(status, &headers(pairs), "issue #1")
+        };
+        assert!(matches!(error(401, &[]), GhError::AuthError(_)));
+        assert!(matches!(error(404, &[]), GhError::NotFound(_)));
+        assert!(matches!(error(410, &[]), GhError::NotFound(_)));
+        assert!(matches!(error(403, &[]), GhError::Forbidden(_)));
+        assert!(matches!(error(500, &[]), GhError::ApiError(_)));
+        assert!(matches!(
+            error(422, &[("retry-after", "30")]),
+            GhError::ApiError(_)
+        ));
+    }
+
+    #[test]
+    fn reads_rate_limit_headers() {
+        let error = |status, pairs: &[(&'static str, &str)]| {
+            status_error(status, &headers(pairs), "issue #1")
+        };
+        let reset = DateTime::from_timestamp(1_700_000_000, 0);
+        assert!(matches!(
+            error(
+                403,
+                &[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1700000000")]
+            ),
+            GhError::RateLimited { reset: r } if r == reset
+        ));
+        assert!(matches!(
+            error(
+                429,
+                &[
+                    ("x-ratelimit-remaining", "0"),
+                    ("x-ratelimit-reset", "soon")
+                ]
+            ),
+            GhError::RateLimited { reset: None }
+        ));
+        assert!(matches!(
+            error(
+                403,
+                &[("x-ratelimit-remaining", "12"), ("retry-after", "30")]
+            ),
+            GhError::SecondaryLimit { retry_after: 30 }
+        ));
+        assert!(matches!(
+            error(429, &[]),
+            GhError::SecondaryLimit {
+                retry_after: DEFAULT_RETRY_AFTER
+            }
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::cache::CachedIssue;
+use crate::references::{find_references, Origin, Reference};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+/// How `ghif graph` writes the reference graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz, e.g. `ghif graph | dot -Tsvg > graph.svg`
+    Dot,
+    /// For Gephi, yEd, Cytoscape and networkx
+    GraphMl,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::GraphMl),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(format!(
+                "Unsupported graph format: {} (use dot, graphml or json)",
+                s
+            )),
+        }
+    }
+}
+
+/// An issue or commit in the graph. Issues that weren't fetched (e.g. in other
+/// repositories) and commits only have what the reference says about them.
+#[derive(Debug, Serialize)]
+pub struct Node {
+    /// `owner/repo#12` or `owner/repo@<sha>`
+    pub id: String,
+    #[serde(flatten)]
+    pub reference: Reference,
+    /// How GitHub shows the reference from the fetched repository, e.g. `#12`
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    pub url: String,
+    /// Whether the issue is in the cache
+    pub fetched: bool,
+}
+
+/// A mention of `target` in the description or a comment of `source`.
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+/// Builds the graph of references between the cached issues: a node per issue,
+/// whether or not it references anything, plus a node per issue or commit they
+/// mention, and an edge per mention.
+pub fn build_graph(cached: &[CachedIssue], include_commits: bool) -> Graph {
+    let mut nodes = BTreeMap::<Reference, Node>::new();
+    let mut edges = BTreeSet::new();
+    let repository = cached
+        .first()
+        .and_then(|c| Origin::of(&c.issue))
+        .map(|origin| origin.repository)
+        .unwrap_or_default();
+
+    for CachedIssue {
+        issue, comments, ..
+    } in cached
+    {
+        let Some(origin) = Origin::of(issue) else {
+            continue;
+        };
+        let source = Reference::Issue {
+            repository: origin.repository.clone(),
+            number: issue.number(),
+        };
+        nodes.insert(
+            source.clone(),
+            Node {
+                id: source.id(),
+                label: source.label(&repository),
+                title: Some(issue.title().to_string()),
+                state: Some(issue.state().to_string()),
+                url: issue.html_url().to_string(),
+                fetched: true,
+                reference: source.clone(),
+            },
+        );
+
+        for target in find_references(issue, comments) {
+            if !include_commits && matches!(target, Reference::Commit { .. }) {
+                continue;
+            }
+            edges.insert(Edge {
+                source: source.id(),
+                target: target.id(),
+            });
+            nodes.entry(target.clone()).or_insert_with(|| Node {
+                id: target.id(),
+                label: target.label(&repository),
+                title: None,
+                state: None,
+                url: target.url(&origin.host),
+                fetched: false,
+                reference: target,
+            });
+        }
+    }
+
+    Graph {
+        nodes: nodes.into_values().collect(),
+        edges: edges.into_iter().collect(),
+    }
+}
+
+impl Graph {
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.render_dot(),
+            GraphFormat::GraphMl => self.render_graphml(),
+            GraphFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default() + "\n",
+        }
+    }
+
+    /// Fetched issues are boxes, green when open and red when closed; issues that
+    /// weren't fetched are dashed and commits are ellipses.
+    fn render_dot(&self) -> String {
+        let mut out = String::from(
+            "digraph issues {\n    rankdir=LR;\n    node [shape=box, fontname=\"Helvetica\"];\n",
+        );
+        for node in &self.nodes {
+            let label = match &node.title {
+                Some(title) => format!(
+                    "{}\\n{}",
+                    escape_dot(&node.label),
+                    escape_dot(&truncate(title, 40))
+                ),
+                None => escape_dot(&node.label),
+            };
+            let style = match (&node.reference, node.state.as_deref()) {
+                (Reference::Commit { .. }, _) => "shape=ellipse",
+                (_, Some("open")) => "color=\"#22863a\"",
+                (_, Some(_)) => "color=\"#cb2431\"",
+                (_, None) => "style=dashed",
+            };
+            out.push_str(&format!(
+                "    \"{}\" [label=\"{}\", URL=\"{}\", {}];\n",
+                escape_dot(&node.id),
+                label,
+                escape_dot(&node.url),
+                style
+            ));
+        }
+        for edge in &self.edges {
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\";\n",
+                escape_dot(&edge.source),
+                escape_dot(&edge.target)
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn render_graphml(&self) -> String {
+        let mut out = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+    <key id="type" for="node" attr.name="type" attr.type="string"/>
+    <key id="label" for="node" attr.name="label" attr.type="string"/>
+    <key id="title" for="node" attr.name="title" attr.type="string"/>
+    <key id="state" for="node" attr.name="state" attr.type="string"/>
+    <key id="url" for="node" attr.name="url" attr.type="string"/>
+    <key id="fetched" for="node" attr.name="fetched" attr.type="boolean"/>
+    <graph id="issues" edgedefault="directed">
+"#,
+        );
+        for node in &self.nodes {
+            let kind = match node.reference {
+                Reference::Issue { .. } => "issue",
+                Reference::Commit { .. } => "commit",
+            };
+            out.push_str(&format!(
+                "        <node id=\"{}\">\n            <data key=\"type\">{}</data>\n            <data key=\"label\">{}</data>\n",
+                escape_xml(&node.id),
+                kind,
+                escape_xml(&node.label)
+            ));
+            if let Some(title) = &node.title {
+                out.push_str(&format!(
+                    "            <data key=\"title\">{}</data>\n",
+                    escape_xml(title)
+                ));
+            }
+            if let Some(state) = &node.state {
+                out.push_str(&format!(
+                    "            <data key=\"state\">{}</data>\n",
+                    escape_xml(state)
+                ));
+            }
+            out.push_str(&format!(
+                "            <data key=\"url\">{}</data>\n            <data key=\"fetched\">{}</data>\n        </node>\n",
+                escape_xml(&node.url),
+                node.fetched
+            ));
+        }
+        for edge in &self.edges {
+            out.push_str(&format!(
+                "        <edge source=\"{}\" target=\"{}\"/>\n",
+                escape_xml(&edge.source),
+                escape_xml(&edge.target)
+            ));
+        }
+        out.push_str("    </graph>\n</graphml>\n");
+        out
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
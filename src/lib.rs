@@ -1,7 +1,36 @@
+pub mod archive;
+pub mod assets;
+pub mod auth;
+pub mod budget;
+pub mod cache;
+pub mod changelog;
+pub mod config;
+pub mod dates;
+pub mod dedupe;
+pub mod doctor;
 pub mod error;
+pub mod files;
 pub mod format;
 pub mod github;
+pub mod graph;
+pub mod logger;
+pub mod naming;
+pub mod plan;
+pub mod prune;
+pub mod references;
+pub mod report;
+pub mod stats;
 
+pub use auth::{AuthOptions, Credentials};
+pub use budget::{estimate_tokens, TokenBudget};
+pub use cache::Cache;
+pub use config::Config;
+pub use dates::{DateFormat, DateStyle, Zone};
 pub use error::GhError;
-pub use format::{get_file_extension, get_writer, FormatWriter, OutputFormat};
+pub use format::{
+    get_file_extension, get_index_file_name, get_writer, FormatWriter, IndexEntry, OutputFormat,
+    SummaryFormat, WriterOptions,
+};
 pub use github::{CommentData, GitHubClient, IssueData};
+pub use logger::{LogFormat, Logger, Verbosity};
+pub use report::Report;
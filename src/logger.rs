@@ -0,0 +1,164 @@
+use chrono::Utc;
+use console::style;
+use indicatif::ProgressBar;
+use serde_json::{json, Map, Value};
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// How status messages are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Colored text for people
+    #[default]
+    Text,
+    /// One JSON object per line on stderr, for log collectors
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unsupported log format: {} (use text or json)", s)),
+        }
+    }
+}
+
+/// How much is written, from `-q` (warnings and errors only) to `-vv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    Quiet,
+    /// Summaries, warnings and the progress bar
+    #[default]
+    Normal,
+    /// Also setup details and a line per issue
+    Verbose,
+    /// Also API request details
+    Debug,
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+}
+
+/// Writes status messages to stderr, as text or as structured JSON lines, so stdout
+/// is left for data.
+///
+/// Every message has an `event` name and optional `fields` (a JSON object) that only
+/// appear in JSON output, so text output stays as readable as before. Text written
+/// while a progress bar is shown is printed above it.
+#[derive(Default)]
+pub struct Logger {
+    format: LogFormat,
+    verbosity: Verbosity,
+    progress: Mutex<Option<ProgressBar>>,
+}
+
+impl Logger {
+    pub fn new(format: LogFormat, verbosity: Verbosity) -> Self {
+        Logger {
+            format,
+            verbosity,
+            progress: Mutex::new(None),
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == LogFormat::Json
+    }
+
+    /// A progress bar that's only drawn for people: it's hidden with `-q`, with JSON
+    /// logs, and when stderr isn't a terminal (e.g. under cron or in CI).
+    pub fn progress_bar(&self, len: u64) -> ProgressBar {
+        let visible = self.format == LogFormat::Text
+            && self.verbosity > Verbosity::Quiet
+            && std::io::stderr().is_terminal();
+        let pb = if visible {
+            ProgressBar::new(len)
+        } else {
+            ProgressBar::hidden()
+        };
+        *self.progress.lock().unwrap_or_else(|e| e.into_inner()) = Some(pb.clone());
+        pb
+    }
+
+    /// A summary message, shown unless `-q` is given. `message` may already be
+    /// styled; styling is stripped for JSON.
+    pub fn info(&self, event: &str, message: impl Display, fields: Value) {
+        self.log(Verbosity::Normal, "info", event, &message, fields);
+    }
+
+    /// Setup details and per-issue messages, shown with `-v`.
+    pub fn verbose(&self, event: &str, message: impl Display, fields: Value) {
+        self.log(Verbosity::Verbose, "info", event, &message, fields);
+    }
+
+    /// API request details, shown with `-vv`.
+    pub fn debug(&self, event: &str, message: impl Display, fields: Value) {
+        self.log(Verbosity::Debug, "debug", event, &message, fields);
+    }
+
+    pub fn warn(&self, event: &str, message: impl Display, fields: Value) {
+        let message = format!("{} {}", style("Warning:").yellow().bold(), message);
+        self.log(Verbosity::Quiet, "warn", event, &message, fields);
+    }
+
+    pub fn error(&self, event: &str, message: impl Display, fields: Value) {
+        let message = format!("{} {}", style("Error:").red().bold(), message);
+        self.log(Verbosity::Quiet, "error", event, &message, fields);
+    }
+
+    fn log(
+        &self,
+        verbosity: Verbosity,
+        level: &str,
+        event: &str,
+        message: &dyn Display,
+        fields: Value,
+    ) {
+        if verbosity > self.verbosity {
+            return;
+        }
+        match self.format {
+            LogFormat::Text => self.text(message),
+            LogFormat::Json => self.json(level, event, message, fields),
+        }
+    }
+
+    fn text(&self, message: &dyn Display) {
+        let progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        match progress.as_ref() {
+            Some(pb) if !pb.is_hidden() && !pb.is_finished() => {
+                pb.suspend(|| eprintln!("{}", message))
+            }
+            _ => eprintln!("{}", message),
+        }
+    }
+
+    fn json(&self, level: &str, event: &str, message: &dyn Display, fields: Value) {
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), json!(Utc::now().to_rfc3339()));
+        line.insert("level".to_string(), json!(level));
+        line.insert("event".to_string(), json!(event));
+        line.insert(
+            "message".to_string(),
+            json!(console::strip_ansi_codes(&message.to_string())),
+        );
+        if let Value::Object(fields) = fields {
+            line.extend(fields);
+        }
+        eprintln!("{}", Value::Object(line));
+    }
+}
@@ -1,111 +1,616 @@
-mod error;
-mod github;
-
 use anyhow::{Context, Result};
-use clap::Parser;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
 use console::style;
-use error::GhError;
-use ghif::{get_file_extension, get_writer, CommentData, GitHubClient, IssueData, OutputFormat};
-use indicatif::{ProgressBar, ProgressStyle};
+use ghif::archive::{commit_message, commit_output, Change, ChangeKind};
+use ghif::assets::localize_assets;
+use ghif::budget::{pack_chunks, Chunk, ChunkEntry, RenderedIssue};
+use ghif::cache::{Cache, CachedIssue, DEFAULT_CACHE_DIR};
+use ghif::changelog::{render_changelog, shipped_at, ReleaseConfig};
+use ghif::config::{Config, Settings};
+use ghif::dedupe::{self, find_duplicates, DEFAULT_THRESHOLD};
+use ghif::doctor::{client_failed, run_checks, Status};
+use ghif::files::{write_atomic, write_if_changed, SavedFile, WriteOutcome};
+use ghif::github::MissingIssue;
+use ghif::graph::{build_graph, GraphFormat};
+use ghif::naming::{FilenameTemplate, DEFAULT_FILENAME_TEMPLATE};
+use ghif::plan::{self, Action, Plan, PlannedIssue, Quota};
+use ghif::prune::{prune_file, remove_empty_dirs, PruneMode, PrunedIssue, ARCHIVE_DIR};
+use ghif::report::ReportError;
+use ghif::stats::{self, StatsOptions};
+use ghif::{
+    get_file_extension, get_index_file_name, get_writer, AuthOptions, CommentData, Credentials,
+    DateFormat, DateStyle, FormatWriter, GhError, GitHubClient, IndexEntry, IssueData, LogFormat,
+    Logger, OutputFormat, Report, SummaryFormat, TokenBudget, Verbosity, WriterOptions, Zone,
+};
+use indicatif::ProgressStyle;
+use octocrab::models::Rate;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Instant;
 
 /// CLI tool to fetch GitHub issues and save them as Markdown files
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Output directory for issue files (default: "./issues")
-    #[arg(short, long, default_value = "./issues")]
-    output: PathBuf,
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Running `ghif` without a subcommand is the same as `ghif fetch`
+    #[command(flatten)]
+    fetch: FetchArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Fetch issues and save them as files (the default)
+    Fetch(Box<FetchArgs>),
+
+    /// Re-render output from the raw cache without calling the API
+    Render(Box<RenderArgs>),
+
+    /// Write release notes from the issues (and pull requests) closed since a tag or date
+    Changelog(Box<ChangelogArgs>),
+
+    /// Summarize issue activity from the cache: volume over time, response and close times, labels, people and stale issues
+    Stats(StatsArgs),
+
+    /// Find clusters of likely duplicate issues in the cache by text similarity
+    Dedupe(DedupeArgs),
+
+    /// Export the references between cached issues (and to commits) as a DOT, GraphML or JSON graph
+    Graph(GraphArgs),
+
+    /// Show the remaining GitHub API rate limit
+    RateLimit(AuthArgs),
+
+    /// Check the credentials, repository access and rate limits, and explain what's missing
+    Doctor(DoctorArgs),
+
+    /// Show the repository ghif would use, from a URL or the current git remote
+    ParseUrl {
+        /// Repository URL or owner/repo; detected from the git remote when omitted
+        url: Option<String>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct FetchArgs {
+    #[command(flatten)]
+    render: RenderArgs,
+
+    #[command(flatten)]
+    auth: AuthArgs,
+
+    /// Repository URL or owner/repo format (e.g., "owner/repo")
+    #[arg(short = 'r', long, help_heading = "Selection")]
+    repository: Option<String>,
 
     /// Comma-separated list of issue numbers to fetch
-    #[arg(short, long)]
+    #[arg(short, long, help_heading = "Selection")]
     issues: Option<String>,
 
     /// Filter issues by state (open/closed)
-    #[arg(short, long, default_value = "open")]
+    #[arg(short, long, default_value = "open", help_heading = "Selection")]
     state: Option<String>,
 
     /// Number of issues to fetch in each batch
-    #[arg(long, default_value = "10")]
+    #[arg(long, default_value = "10", help_heading = "Selection")]
     batch_size: usize,
 
-    /// Skip existing files
-    #[arg(long, default_value_t = true)]
+    /// Skip existing files; `--skip-existing=false` fetches and rewrites them
+    #[arg(long, default_value_t = true, action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true", help_heading = "Output")]
     skip_existing: bool,
 
-    /// Output format (markdown/xml)
-    #[arg(short, long, default_value = "markdown")]
-    format: String,
+    /// Archive (the default) or delete files of issues no longer fetched: deleted, transferred, or no longer matching --state
+    #[arg(long, num_args = 0..=1, default_missing_value = "archive", value_parser = PruneMode::from_str, help_heading = "Output")]
+    prune: Option<PruneMode>,
 
-    /// Repository URL or owner/repo format (e.g., "owner/repo")
+    /// With --prune, also prune issues that aren't found, which may only mean the token can no longer read them
+    #[arg(long, help_heading = "Output")]
+    prune_unavailable: bool,
+
+    /// Commit the output directory to git after the run (initializing a repository if needed), summarizing new, updated and closed issues
+    #[arg(long, help_heading = "Output")]
+    git_commit: bool,
+
+    /// Fetch which comments are hidden (minimized) on GitHub even without --exclude-minimized, so `render --exclude-minimized` can leave them out; requires GITHUB_TOKEN
+    #[arg(long, help_heading = "Comments")]
+    with_minimized_status: bool,
+
+    /// List the issues and files a fetch would write, and the API calls it needs, without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+struct DoctorArgs {
+    /// Repository URL or owner/repo format (detected from the git remote when omitted)
     #[arg(short = 'r', long)]
     repository: Option<String>,
+
+    #[command(flatten)]
+    auth: AuthArgs,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    run().await.with_context(|| "Application error occurred")?;
-    Ok(())
+#[derive(Args, Debug)]
+struct ChangelogArgs {
+    /// Repository URL or owner/repo format (detected from the git remote when omitted)
+    #[arg(short = 'r', long)]
+    repository: Option<String>,
+
+    /// Start of the range: a tag, branch or commit, or a date (e.g. 2024-01-31)
+    #[arg(long, required_unless_present = "milestone")]
+    since: Option<String>,
+
+    /// End of the range, like --since (default: now)
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Only issues in this milestone, by title
+    #[arg(long)]
+    milestone: Option<String>,
+
+    /// Include merged pull requests
+    #[arg(long)]
+    include_prs: bool,
+
+    /// Categories config in GitHub's release.yml format (default: .github/release.yml of the current repository)
+    #[arg(long)]
+    release_config: Option<PathBuf>,
+
+    /// Heading of the changelog (default: "Changes since <since>")
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Write the changelog to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// How dates are written: default, rfc3339, relative, or a strftime pattern
+    #[arg(long, default_value = "%Y-%m-%d", value_parser = DateStyle::from_str)]
+    date_format: DateStyle,
+
+    #[command(flatten)]
+    auth: AuthArgs,
 }
 
-async fn run() -> Result<()> {
-    let args = Args::parse();
-    println!("{} Starting ghif...", style("Info:").cyan().bold());
-    println!(
-        "{} Output directory: {}",
-        style("Info:").cyan().bold(),
-        args.output.display()
-    );
+/// Where the commands that only read the cache of an earlier fetch find it.
+#[derive(Args, Debug)]
+struct CacheArgs {
+    /// Output directory of an earlier fetch, whose cache is read (default: "./issues")
+    #[arg(short, long, default_value = "./issues")]
+    output: PathBuf,
 
-    std::fs::create_dir_all(&args.output).with_context(|| {
-        format!(
-            "Failed to create output directory: {}",
-            args.output.display()
+    /// Directory the raw fetched data is cached in (default: <output>/.ghif-cache)
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Named profile from .ghif.toml or ~/.config/ghif/config.toml to apply
+    #[arg(short, long)]
+    profile: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct StatsArgs {
+    #[command(flatten)]
+    cache: CacheArgs,
+
+    /// Report format (table/json/markdown)
+    #[arg(short, long, default_value = "table", value_parser = SummaryFormat::from_str)]
+    format: SummaryFormat,
+
+    /// Only count activity from this date on (e.g. 2024-01-31, or an RFC 3339 time)
+    #[arg(long, value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+
+    /// Open issues without updates for this many days are listed as stale
+    #[arg(long, default_value = "90")]
+    stale_days: i64,
+
+    /// How many labels, reporters and commenters to list
+    #[arg(long, default_value = "10")]
+    top: usize,
+}
+
+#[derive(Args, Debug)]
+struct DedupeArgs {
+    #[command(flatten)]
+    cache: CacheArgs,
+
+    /// Report format (table/json/markdown)
+    #[arg(short, long, default_value = "table", value_parser = SummaryFormat::from_str)]
+    format: SummaryFormat,
+
+    /// Similarity from 0 to 1 at which issues count as likely duplicates; lower finds more, with more false positives
+    #[arg(long, default_value_t = DEFAULT_THRESHOLD, value_parser = parse_threshold)]
+    threshold: f64,
+
+    /// Only report clusters with at least one open issue
+    #[arg(long)]
+    open: bool,
+}
+
+#[derive(Args, Debug)]
+struct GraphArgs {
+    #[command(flatten)]
+    cache: CacheArgs,
+
+    /// Graph format (dot/graphml/json)
+    #[arg(short, long, default_value = "dot", value_parser = GraphFormat::from_str)]
+    format: GraphFormat,
+
+    /// Leave out commits, keeping only references between issues
+    #[arg(long)]
+    no_commits: bool,
+}
+
+/// Credential options. Without them, ghif falls back to `GITHUB_TOKEN`/`GH_TOKEN`,
+/// the gh CLI and git's credential helpers.
+#[derive(Args, Debug)]
+struct AuthArgs {
+    /// GitHub token to use instead of the environment, gh CLI or git credentials
+    #[arg(long, help_heading = "Authentication")]
+    token: Option<String>,
+
+    /// GitHub App ID, to authenticate as an app installation
+    #[arg(long, env = "GHIF_APP_ID", help_heading = "Authentication")]
+    app_id: Option<u64>,
+
+    /// GitHub App private key: a path to the PEM file, or its contents
+    #[arg(
+        long,
+        env = "GHIF_APP_KEY",
+        hide_env_values = true,
+        help_heading = "Authentication"
+    )]
+    app_key: Option<String>,
+
+    /// GitHub App installation ID (looked up from the repository when omitted)
+    #[arg(
+        long,
+        env = "GHIF_APP_INSTALLATION_ID",
+        help_heading = "Authentication"
+    )]
+    app_installation_id: Option<u64>,
+}
+
+impl AuthArgs {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::resolve(&AuthOptions {
+            token: self.token.clone(),
+            app_id: self.app_id,
+            app_key: self.app_key.clone(),
+            app_installation_id: self.app_installation_id,
+        })?)
+    }
+}
+
+/// Options shared by `fetch` and `render`: where output goes and how it's rendered.
+#[derive(Args, Debug)]
+struct RenderArgs {
+    /// Output directory for issue files (default: "./issues")
+    #[arg(short, long, default_value = "./issues", help_heading = "Output")]
+    output: PathBuf,
+
+    /// Output format (markdown/xml/chunks/html/csv/tsv)
+    #[arg(short, long, default_value = "markdown", help_heading = "Output")]
+    format: String,
+
+    /// How dates are written: default, rfc3339, relative, or a strftime pattern (e.g. "%Y-%m-%d")
+    #[arg(long, default_value = "default", value_parser = DateStyle::from_str, help_heading = "Output")]
+    date_format: DateStyle,
+
+    /// Time zone dates are shown in: utc, local, or an offset such as +09:00
+    #[arg(long, default_value = "utc", value_parser = Zone::from_str, help_heading = "Output")]
+    timezone: Zone,
+
+    /// Where each issue's file goes, e.g. "{number}" or "{state}/{number}-{slug}" (see the README for placeholders)
+    #[arg(long, default_value = DEFAULT_FILENAME_TEMPLATE, value_parser = FilenameTemplate::from_str, help_heading = "Output")]
+    filename_template: FilenameTemplate,
+
+    /// Download images and attachments into assets/<issue>/ and link to the local copies
+    #[arg(long, help_heading = "Output")]
+    download_assets: bool,
+
+    /// Write all issues into combined files, split into chunks of --max-tokens
+    #[arg(long, help_heading = "Output")]
+    combine: bool,
+
+    /// Directory the raw fetched data is cached in (default: <output>/.ghif-cache)
+    #[arg(long, help_heading = "Output")]
+    cache_dir: Option<PathBuf>,
+
+    /// Maximum estimated tokens per rendered issue (and per combined chunk)
+    #[arg(long, help_heading = "Token budget")]
+    max_tokens: Option<usize>,
+
+    /// Number of first and last comments to keep when truncating a thread
+    #[arg(long, default_value = "3", help_heading = "Token budget")]
+    keep_comments: usize,
+
+    /// Maximum chunk length in characters for the chunks format
+    #[arg(long, default_value = "1000", help_heading = "Token budget")]
+    chunk_size: usize,
+
+    /// Characters shared between consecutive chunks for the chunks format
+    #[arg(long, default_value = "200", help_heading = "Token budget")]
+    chunk_overlap: usize,
+
+    /// Leave out comments written by bots (e.g. CI and dependency bots)
+    #[arg(long, help_heading = "Comments")]
+    exclude_bots: bool,
+
+    /// Leave out comments hidden (minimized) on GitHub; requires GITHUB_TOKEN
+    #[arg(long, help_heading = "Comments")]
+    exclude_minimized: bool,
+
+    /// Write a JSON report of the run (issues written, skipped and failed, API calls) to this file
+    #[arg(long, help_heading = "Reporting")]
+    report: Option<PathBuf>,
+
+    /// How status messages are written: text, or json (one object per line on stderr)
+    #[arg(long, default_value = "text", value_parser = LogFormat::from_str, help_heading = "Reporting")]
+    log_format: LogFormat,
+
+    /// Only print warnings and errors
+    #[arg(short, long, conflicts_with = "verbose", help_heading = "Reporting")]
+    quiet: bool,
+
+    /// Print more: -v adds a line per issue, -vv also API request details
+    #[arg(short, long, action = ArgAction::Count, help_heading = "Reporting")]
+    verbose: u8,
+
+    /// Named profile from .ghif.toml or ~/.config/ghif/config.toml to apply
+    #[arg(short, long)]
+    profile: Option<String>,
+}
+
+impl RenderArgs {
+    fn logger(&self) -> Logger {
+        Logger::new(
+            self.log_format,
+            Verbosity::from_flags(self.quiet, self.verbose),
         )
-    })?;
+    }
 
-    let repo = if let Some(repo) = args.repository {
-        println!(
-            "{} Using provided repository: {}",
+    /// What links from a rendered issue (to assets or the index) need in front,
+    /// given where its file is written.
+    fn link_prefix(&self) -> String {
+        if self.combine {
+            String::new()
+        } else {
+            self.filename_template.link_prefix()
+        }
+    }
+
+    fn cache(&self) -> Cache {
+        Cache::new(
+            self.cache_dir
+                .clone()
+                .unwrap_or_else(|| self.output.join(DEFAULT_CACHE_DIR)),
+        )
+    }
+}
+
+impl FetchArgs {
+    /// Whether comments' minimized status is asked from the GraphQL API.
+    fn with_minimized_status(&self) -> bool {
+        self.with_minimized_status || self.render.exclude_minimized
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut log = Logger::default();
+    match run(&mut log).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let report = report_error(&e);
+            log.error(
+                "run_failed",
+                &report.message,
+                json!({ "kind": report.kind, "exit_code": report.exit_code }),
+            );
+            let retryable = e
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<GhError>())
+                .is_some_and(GhError::is_retryable);
+            if retryable && !log.is_json() {
+                eprintln!(
+                    "{} This is usually temporary; try again later.",
+                    style("Tip:").cyan().bold()
+                );
+            }
+            // Exit codes are documented in the README, for scripts to act on
+            ExitCode::from(report.exit_code)
+        }
+    }
+}
+
+/// Runs the selected command. Once the options are loaded, `log` is switched to the
+/// requested log format so errors are reported in it too.
+async fn run(log: &mut Logger) -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+
+    match cli.command {
+        None => {
+            let args = load_settings(cli.fetch, &matches)?;
+            *log = args.render.logger();
+            run_fetch(args, log).await
+        }
+        Some(Command::Fetch(args)) => {
+            let matches = matches
+                .subcommand_matches("fetch")
+                .expect("fetch subcommand was parsed");
+            let args = load_settings(*args, matches)?;
+            *log = args.render.logger();
+            run_fetch(args, log).await
+        }
+        Some(Command::Render(args)) => {
+            let matches = matches
+                .subcommand_matches("render")
+                .expect("render subcommand was parsed");
+            let args = load_render_settings(*args, matches)?;
+            *log = args.logger();
+            run_render(args, log).await
+        }
+        Some(Command::Stats(args)) => {
+            let matches = matches
+                .subcommand_matches("stats")
+                .expect("stats subcommand was parsed");
+            run_stats(args, matches)
+        }
+        Some(Command::Dedupe(args)) => {
+            let matches = matches
+                .subcommand_matches("dedupe")
+                .expect("dedupe subcommand was parsed");
+            run_dedupe(args, matches)
+        }
+        Some(Command::Graph(args)) => {
+            let matches = matches
+                .subcommand_matches("graph")
+                .expect("graph subcommand was parsed");
+            let cached = load_cached(args.cache, matches)?;
+            print!(
+                "{}",
+                build_graph(&cached, !args.no_commits).render(args.format)
+            );
+            Ok(())
+        }
+        Some(Command::RateLimit(auth)) => {
+            let repo = detect_github_repo().ok();
+            let client = GitHubClient::new(
+                &auth.credentials()?,
+                repo.as_deref().and_then(|repo| repo.split_once('/')),
+            )
+            .await?;
+            println!("{}", client.get_rate_limit_info().await?);
+            Ok(())
+        }
+        Some(Command::Changelog(args)) => run_changelog(*args, log).await,
+        Some(Command::Doctor(args)) => run_doctor(args).await,
+        Some(Command::ParseUrl { url }) => {
+            let repo = match url {
+                Some(url) => resolve_repository(&url)?,
+                None => detect_github_repo()?,
+            };
+            println!("{}", repo);
+            Ok(())
+        }
+    }
+}
+
+async fn run_fetch(args: FetchArgs, log: &Logger) -> Result<()> {
+    let mut report = Report::new("fetch", &args.render.format);
+    let result = fetch(&args, log, &mut report).await;
+    finish_report(&args.render, log, report, &result)?;
+    result
+}
+
+async fn fetch(args: &FetchArgs, log: &Logger, report: &mut Report) -> Result<()> {
+    log.verbose(
+        "started",
+        format!("{} Starting ghif...", style("Info:").cyan().bold()),
+        json!({}),
+    );
+    log.verbose(
+        "output_dir",
+        format!(
+            "{} Output directory: {}",
             style("Info:").cyan().bold(),
-            repo
+            args.render.output.display()
+        ),
+        json!({ "path": args.render.output }),
+    );
+
+    if args.prune.is_some() {
+        if args.issues.is_some() {
+            return Err(GhError::ConfigError(
+                "--prune reconciles against the full issue list, so it can't be used with --issues"
+                    .to_string(),
+            )
+            .into());
+        }
+        let tabular = matches!(
+            args.render.format.parse(),
+            Ok(OutputFormat::Csv | OutputFormat::Tsv)
         );
-        repo
+        if tabular || args.render.combine {
+            return Err(GhError::ConfigError(
+                "--prune needs per-issue files, so it can't be used with --combine or csv/tsv"
+                    .to_string(),
+            )
+            .into());
+        }
+    }
+
+    if !args.dry_run {
+        std::fs::create_dir_all(&args.render.output).with_context(|| {
+            format!(
+                "Failed to create output directory: {}",
+                args.render.output.display()
+            )
+        })?;
+    }
+
+    let repo = if let Some(repo) = &args.repository {
+        log.verbose(
+            "repository_given",
+            format!(
+                "{} Using provided repository: {}",
+                style("Info:").cyan().bold(),
+                repo
+            ),
+            json!({ "repository": repo }),
+        );
+        resolve_repository(repo)?
     } else {
-        println!(
-            "{} Attempting to detect GitHub repository...",
-            style("Info:").cyan().bold()
+        log.verbose(
+            "repository_detect",
+            format!(
+                "{} Attempting to detect GitHub repository...",
+                style("Info:").cyan().bold()
+            ),
+            json!({}),
         );
         detect_github_repo().with_context(|| "Failed to detect GitHub repository")?
     };
 
     let (owner, repo_name) = repo.split_once('/').ok_or_else(|| {
-        println!(
-            "{} Invalid repository format: {}",
-            style("Error:").red().bold(),
+        GhError::InvalidRepoUrl(format!(
+            "Repository should be in format 'owner/repo', got {}",
             repo
-        );
-        GhError::InvalidRepoUrl("Repository should be in format 'owner/repo'".to_string())
+        ))
     })?;
+    report.repository = Some(format!("{}/{}", owner, repo_name));
 
-    println!(
-        "{} Repository: {}/{}",
-        style("Info:").cyan().bold(),
-        owner,
-        repo_name
+    log.verbose(
+        "repository",
+        format!(
+            "{} Repository: {}/{}",
+            style("Info:").cyan().bold(),
+            owner,
+            repo_name
+        ),
+        json!({ "owner": owner, "repo": repo_name }),
     );
 
-    let issue_numbers = args.issues.map(|s| {
+    let issue_numbers = args.issues.as_deref().map(|s| {
         s.split(',')
             .filter_map(|n| {
                 n.trim()
                     .parse::<u32>()
                     .map_err(|e| {
-                        eprintln!(
-                            "{} Invalid issue number '{}': {}",
-                            style("Warning:").yellow().bold(),
-                            style(n).red(),
-                            e
+                        log.warn(
+                            "invalid_issue_number",
+                            format!("Invalid issue number '{}': {}", style(n).red(), e),
+                            json!({ "value": n }),
                         );
                     })
                     .ok()
@@ -113,39 +618,621 @@ async fn run() -> Result<()> {
             .collect::<Vec<_>>()
     });
 
-    let client = match GitHubClient::new().await {
-        Ok(client) => {
-            println!(
-                "{} GitHub client initialized successfully",
-                style("Info:").cyan().bold()
-            );
-            client
-        }
-        Err(e) => {
-            println!(
-                "{} Failed to initialize GitHub client: {}",
-                style("Error:").red().bold(),
-                e
+    let credentials = args.auth.credentials()?;
+    if let Credentials::Anonymous = credentials {
+        log.warn(
+            "anonymous",
+            "Running without a GitHub token. API rate limits will be restricted.",
+            json!({}),
+        );
+        if !log.is_json() {
+            eprintln!(
+                "{} To increase rate limits, set GITHUB_TOKEN, pass --token, or log in with `gh auth login`.",
+                style("Tip:").cyan().bold()
             );
-            return Err(e.into());
         }
-    };
+    }
+    let client = GitHubClient::new(&credentials, Some((owner, repo_name)))
+        .await
+        .with_context(|| "Failed to initialize GitHub client")?;
+    log.verbose(
+        "client_ready",
+        format!(
+            "{} GitHub client initialized successfully ({})",
+            style("Info:").cyan().bold(),
+            credentials.describe()
+        ),
+        json!({ "credentials": credentials.describe() }),
+    );
 
-    println!("{}", client.get_rate_limit_info().await?);
+    let rate_limit = client.rate_limits().await?;
+    log.verbose(
+        "rate_limit",
+        client.get_rate_limit_info().await?,
+        json!({ "remaining": rate_limit.rate.remaining, "limit": rate_limit.rate.limit }),
+    );
 
-    fetch_issues(
+    let result = fetch_issues(
+        args,
+        log,
+        report,
         &client,
         owner,
         repo_name,
-        &args.output,
         issue_numbers.as_deref(),
         args.state.as_deref(),
     )
-    .await?;
+    .await;
+
+    report.api_calls = client.requests_made();
+    if result.is_ok() {
+        report.rate_limit_remaining = client
+            .rate_limits()
+            .await
+            .ok()
+            .map(|rate_limit| rate_limit.rate.remaining);
+    }
+    let changes = result?;
+
+    if args.git_commit && !args.dry_run {
+        commit_archive(args, log, report, &changes)?;
+    }
+    Ok(())
+}
 
+/// Commits the output directory with a message summarizing what the run changed.
+fn commit_archive(
+    args: &FetchArgs,
+    log: &Logger,
+    report: &mut Report,
+    changes: &[Change],
+) -> Result<()> {
+    let repository = report.repository.as_deref().unwrap_or("repository");
+    let message = commit_message(repository, changes, &report.pruned);
+    let cache = args.render.cache();
+    let commit = commit_output(&args.render.output, Some(cache.dir()), &message)?;
+
+    match &commit {
+        Some(hash) => log.info(
+            "committed",
+            format!(
+                "{} {} {}",
+                style("Committed").green(),
+                style(hash).cyan(),
+                message.lines().next().unwrap_or_default()
+            ),
+            json!({ "commit": hash, "changes": changes.len() }),
+        ),
+        None => log.info(
+            "nothing_to_commit",
+            "No changes to commit in the output directory",
+            json!({}),
+        ),
+    }
+    report.commit = commit;
+    Ok(())
+}
+
+/// Completes the report, warns about partial failures and writes the report file if
+/// one was requested.
+fn finish_report(
+    args: &RenderArgs,
+    log: &Logger,
+    mut report: Report,
+    result: &Result<()>,
+) -> Result<()> {
+    report.finish(result.as_ref().err().map(report_error));
+
+    if report.counts.failed > 0 {
+        log.warn(
+            "partial_failure",
+            format!(
+                "{} of {} issues failed",
+                report.counts.failed, report.counts.found
+            ),
+            json!({ "failed": report.counts.failed, "found": report.counts.found }),
+        );
+    }
+
+    if let Some(path) = &args.report {
+        write_atomic(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to write report: {}", path.display()))?;
+        log.info(
+            "report_saved",
+            format!("Saved run report to {}", path.display()),
+            json!({ "path": path }),
+        );
+    }
+    Ok(())
+}
+
+fn report_error(error: &anyhow::Error) -> ReportError {
+    let gh_error = error.chain().find_map(|c| c.downcast_ref::<GhError>());
+    ReportError {
+        kind: gh_error.map_or("other", GhError::kind),
+        message: format!("{:#}", error),
+        exit_code: gh_error.map_or(1, GhError::exit_code),
+    }
+}
+
+async fn run_doctor(args: DoctorArgs) -> Result<()> {
+    let repo = match &args.repository {
+        Some(repo) => Some(resolve_repository(repo)?),
+        None => detect_github_repo().ok(),
+    };
+    let repo = repo.as_deref().and_then(|repo| repo.split_once('/'));
+    let credentials = args.auth.credentials()?;
+    let checks = match GitHubClient::new(&credentials, repo).await {
+        Ok(client) => run_checks(&client, &credentials, repo).await,
+        Err(e) => vec![client_failed(&credentials, &e)],
+    };
+    for check in &checks {
+        let status = match check.status {
+            Status::Ok => style("ok").green().bold(),
+            Status::Warn => style("warn").yellow().bold(),
+            Status::Fail => style("fail").red().bold(),
+        };
+        println!(
+            "{:>4}  {}: {}",
+            status,
+            style(check.name).bold(),
+            check.detail
+        );
+        if let Some(hint) = &check.hint {
+            println!("      {} {}", style("→").cyan(), hint);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed", failed);
+    }
     Ok(())
 }
 
+async fn run_changelog(args: ChangelogArgs, log: &Logger) -> Result<()> {
+    let repo = match &args.repository {
+        Some(repo) => resolve_repository(repo)?,
+        None => detect_github_repo().with_context(|| "Failed to detect GitHub repository")?,
+    };
+    let (owner, repo) = repo.split_once('/').ok_or_else(|| {
+        GhError::InvalidRepoUrl(format!(
+            "Repository should be in format 'owner/repo', got {}",
+            repo
+        ))
+    })?;
+
+    let config = match args
+        .release_config
+        .clone()
+        .or_else(|| detect_repo_root().and_then(|root| ReleaseConfig::find(&root)))
+    {
+        Some(path) => {
+            log.verbose(
+                "release_config",
+                format!("Using categories from {}", path.display()),
+                json!({ "path": path }),
+            );
+            ReleaseConfig::load(&path)?
+        }
+        None => ReleaseConfig::default(),
+    };
+
+    let client = GitHubClient::new(&args.auth.credentials()?, Some((owner, repo))).await?;
+    let since = match &args.since {
+        Some(since) => Some(resolve_point(&client, owner, repo, since).await?),
+        None => None,
+    };
+    let until = match &args.until {
+        Some(until) => resolve_point(&client, owner, repo, until).await?,
+        None => Utc::now(),
+    };
+    let milestone = match &args.milestone {
+        Some(title) => Some(client.milestone_number(owner, repo, title).await?),
+        None => None,
+    };
+
+    let closed = client
+        .fetch_closed(owner, repo, since, milestone, args.include_prs)
+        .await?;
+    let mut shipped = closed
+        .iter()
+        .filter(|issue| {
+            shipped_at(issue)
+                .is_some_and(|date| since.is_none_or(|since| *date >= since) && *date <= until)
+        })
+        .collect::<Vec<_>>();
+    shipped.sort_by_key(|issue| shipped_at(issue).copied());
+    log.info(
+        "changelog",
+        format!(
+            "{} {} issues closed in range ({} API requests)",
+            style("Found").cyan().bold(),
+            shipped.len(),
+            client.requests_made()
+        ),
+        json!({ "issues": shipped.len(), "api_calls": client.requests_made() }),
+    );
+
+    let title = args
+        .title
+        .clone()
+        .unwrap_or_else(|| match (&args.milestone, &args.since) {
+            (Some(milestone), Some(since)) => format!("{} (since {})", milestone, since),
+            (Some(milestone), None) => milestone.clone(),
+            (None, since) => format!("Changes since {}", since.as_deref().unwrap_or_default()),
+        });
+    let changelog = render_changelog(
+        &title,
+        &config.categorize(&shipped),
+        &DateFormat::new(args.date_format.clone(), Zone::Utc),
+    );
+
+    match &args.output {
+        Some(path) => {
+            write_atomic(path, changelog)
+                .with_context(|| format!("Failed to write changelog: {}", path.display()))?;
+            log.info(
+                "changelog_saved",
+                format!("Saved changelog to {}", path.display()),
+                json!({ "path": path }),
+            );
+        }
+        None => print!("{}", changelog),
+    }
+    Ok(())
+}
+
+/// Parses a range boundary: an RFC 3339 time, a date (midnight UTC), or else a git
+/// ref looked up on GitHub.
+async fn resolve_point(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    value: &str,
+) -> Result<DateTime<Utc>> {
+    match parse_time(value) {
+        Ok(time) => Ok(time),
+        Err(_) => Ok(client.ref_date(owner, repo, value).await?),
+    }
+}
+
+/// Parses an RFC 3339 time, or a date as midnight UTC.
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| format!("Invalid date: {} (use YYYY-MM-DD or RFC 3339)", value))
+}
+
+fn parse_threshold(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(threshold) if (0.0..=1.0).contains(&threshold) => Ok(threshold),
+        _ => Err(format!("Invalid threshold: {} (use 0 to 1)", value)),
+    }
+}
+
+/// Loads the cache of an earlier fetch, for the commands that only analyze it.
+fn load_cached(args: CacheArgs, matches: &ArgMatches) -> Result<Vec<CachedIssue>> {
+    let args = load_cache_settings(args, matches)?;
+    let cache = Cache::new(
+        args.cache_dir
+            .unwrap_or_else(|| args.output.join(DEFAULT_CACHE_DIR)),
+    );
+    let cached = cache.load_all()?;
+    if cached.is_empty() {
+        anyhow::bail!(
+            "No cached issues in {}; run `ghif fetch` first",
+            cache.dir().display()
+        );
+    }
+    Ok(cached)
+}
+
+fn run_stats(args: StatsArgs, matches: &ArgMatches) -> Result<()> {
+    let cached = load_cached(args.cache, matches)?;
+
+    let stats = stats::compute(
+        &cached,
+        &StatsOptions {
+            now: Utc::now(),
+            since: args.since,
+            stale_days: args.stale_days,
+            top: args.top,
+        },
+    );
+    match args.format {
+        SummaryFormat::Table => print!("{}", stats.render_table()),
+        SummaryFormat::Markdown => print!("{}", stats.render_markdown()),
+        SummaryFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+    Ok(())
+}
+
+fn run_dedupe(args: DedupeArgs, matches: &ArgMatches) -> Result<()> {
+    let cached = load_cached(args.cache, matches)?;
+    let issues = cached.iter().map(|c| &c.issue).collect::<Vec<_>>();
+
+    let mut clusters = find_duplicates(&issues, args.threshold);
+    if args.open {
+        clusters.retain(|cluster| cluster.issues.iter().any(|issue| issue.state == "open"));
+    }
+    match args.format {
+        SummaryFormat::Table => print!("{}", dedupe::render_table(&clusters)),
+        SummaryFormat::Markdown => print!("{}", dedupe::render_markdown(&clusters)),
+        SummaryFormat::Json => println!("{}", serde_json::to_string_pretty(&clusters)?),
+    }
+    Ok(())
+}
+
+async fn run_render(args: RenderArgs, log: &Logger) -> Result<()> {
+    let mut report = Report::new("render", &args.format);
+    let result = render(&args, log, &mut report).await;
+    finish_report(&args, log, report, &result)?;
+    result
+}
+
+async fn render(args: &RenderArgs, log: &Logger, report: &mut Report) -> Result<()> {
+    let cache = args.cache();
+    let cached = cache.load_all()?;
+    if cached.is_empty() {
+        anyhow::bail!(
+            "No cached issues in {}; run `ghif fetch` first",
+            cache.dir().display()
+        );
+    }
+
+    std::fs::create_dir_all(&args.output).with_context(|| {
+        format!(
+            "Failed to create output directory: {}",
+            args.output.display()
+        )
+    })?;
+    log.info(
+        "render_started",
+        format!(
+            "{} Rendering {} cached issues from {}",
+            style("Info:").cyan().bold(),
+            style(cached.len()).cyan(),
+            cache.dir().display()
+        ),
+        json!({ "issues": cached.len(), "cache_dir": cache.dir() }),
+    );
+
+    if args.exclude_minimized {
+        let unknown = cached
+            .iter()
+            .filter(|c| !c.minimized_status && !c.comments.is_empty())
+            .count();
+        if unknown > 0 {
+            log.warn(
+                "minimized_status_unknown",
+                format!(
+                    "{} cached issues were fetched without minimized status, so hidden comments in them are kept; fetch again with --with-minimized-status or --exclude-minimized",
+                    unknown
+                ),
+                json!({ "issues": unknown }),
+            );
+        }
+    }
+
+    let mut output = Output::new(args, log)?;
+    let mut kept = Vec::new();
+    let mut incomplete = Vec::new();
+    for CachedIssue {
+        mut issue,
+        mut comments,
+        ..
+    } in cached
+    {
+        let started = Instant::now();
+        let number = issue.number();
+        // Issues fetched as csv/tsv, or skipped as existing before the cache held
+        // them, may be cached without their comments; existing files are kept rather
+        // than rendered without
+        let uncached =
+            output.writer.writes_issue_files() && comments.is_empty() && issue.comment_count() > 0;
+        if uncached && !args.combine {
+            let path = args.output.join(output.issue_path(&issue));
+            if path.exists() {
+                output.remove_stale(number, &path)?;
+                output.indexed.push(issue);
+                report.skipped(number, "comments not cached");
+                kept.push(number);
+                continue;
+            }
+        }
+        if uncached {
+            incomplete.push(number);
+        }
+        if output.writer.writes_issue_files() {
+            retain_comments(args, &mut comments);
+            if args.download_assets {
+                localize_assets(
+                    None,
+                    &args.output,
+                    &args.link_prefix(),
+                    &mut issue,
+                    &mut comments,
+                )
+                .await?;
+            }
+        }
+        let path = output.add(issue, &comments)?;
+        report.written(number, path, started);
+    }
+    if !kept.is_empty() {
+        let warning = format!(
+            "Kept the existing files of {} issues whose comments aren't cached; fetch them with --skip-existing=false to render them",
+            kept.len()
+        );
+        log.warn("comments_not_cached", &warning, json!({ "issues": kept }));
+        report.warnings.push(warning);
+    }
+    if !incomplete.is_empty() {
+        let warning = format!(
+            "Rendered {} issues without their comments, which aren't cached; fetch them with --skip-existing=false in a format with comments to include them",
+            incomplete.len()
+        );
+        log.warn(
+            "comments_not_cached",
+            &warning,
+            json!({ "issues": incomplete }),
+        );
+        report.warnings.push(warning);
+    }
+    report.removed.append(&mut output.removed);
+    report.files.extend(output.finish()?);
+
+    log.info(
+        "finished",
+        format!(
+            "{}",
+            style("All issues rendered successfully!").green().bold()
+        ),
+        json!({}),
+    );
+    Ok(())
+}
+
+/// Fills in fetch options not given on the command line from the config files and
+/// the selected profile.
+fn load_settings(mut args: FetchArgs, matches: &ArgMatches) -> Result<FetchArgs> {
+    let settings = config_settings(args.render.profile.as_deref())?;
+    apply_settings(&mut args, matches, &settings)?;
+
+    Ok(args)
+}
+
+/// Like [`load_settings`], for the options `render` takes.
+fn load_render_settings(mut args: RenderArgs, matches: &ArgMatches) -> Result<RenderArgs> {
+    let settings = config_settings(args.profile.as_deref())?;
+    apply_render_settings(&mut args, matches, &settings)?;
+
+    Ok(args)
+}
+
+/// Like [`load_settings`], for where the commands that only read the cache find it.
+fn load_cache_settings(mut args: CacheArgs, matches: &ArgMatches) -> Result<CacheArgs> {
+    let settings = config_settings(args.profile.as_deref())?;
+    apply_cache_settings(&mut args, matches, &settings);
+
+    Ok(args)
+}
+
+fn config_settings(profile: Option<&str>) -> Result<Settings> {
+    let config = Config::discover(detect_repo_root().as_deref())?;
+    Ok(config.settings(profile)?)
+}
+
+/// Sets `$field` from the config unless it was given on the command line.
+macro_rules! apply {
+    ($args:expr, $matches:expr, $settings:expr, $($field:ident),*) => {
+        $(if let Some(value) = $settings.$field.clone().filter(|_| {
+            $matches.value_source(stringify!($field)) != Some(ValueSource::CommandLine)
+        }) {
+            $args.$field = value.into();
+        })*
+    };
+}
+
+fn apply_settings(args: &mut FetchArgs, matches: &ArgMatches, settings: &Settings) -> Result<()> {
+    apply!(
+        args,
+        matches,
+        settings,
+        batch_size,
+        skip_existing,
+        git_commit,
+        prune_unavailable,
+        with_minimized_status
+    );
+    apply!(args, matches, settings, repository, issues, state);
+    if let Some(value) = settings
+        .prune
+        .as_ref()
+        .filter(|_| matches.value_source("prune") != Some(ValueSource::CommandLine))
+    {
+        args.prune = Some(value.parse().map_err(anyhow::Error::msg)?);
+    }
+
+    apply_render_settings(&mut args.render, matches, settings)
+}
+
+fn apply_render_settings(
+    args: &mut RenderArgs,
+    matches: &ArgMatches,
+    settings: &Settings,
+) -> Result<()> {
+    let from_config = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+    apply!(
+        args,
+        matches,
+        settings,
+        output,
+        format,
+        keep_comments,
+        chunk_size,
+        chunk_overlap,
+        exclude_bots,
+        exclude_minimized,
+        download_assets,
+        combine,
+        max_tokens,
+        cache_dir
+    );
+
+    if let Some(value) = settings
+        .date_format
+        .as_ref()
+        .filter(|_| from_config("date_format"))
+    {
+        args.date_format = value.parse().map_err(anyhow::Error::msg)?;
+    }
+    if let Some(value) = settings
+        .timezone
+        .as_ref()
+        .filter(|_| from_config("timezone"))
+    {
+        args.timezone = value.parse().map_err(anyhow::Error::msg)?;
+    }
+    if let Some(value) = settings
+        .filename_template
+        .as_ref()
+        .filter(|_| from_config("filename_template"))
+    {
+        args.filename_template = value.parse().map_err(anyhow::Error::msg)?;
+    }
+    if let Some(value) = settings
+        .log_format
+        .as_ref()
+        .filter(|_| from_config("log_format"))
+    {
+        args.log_format = value.parse().map_err(anyhow::Error::msg)?;
+    }
+
+    Ok(())
+}
+
+fn apply_cache_settings(args: &mut CacheArgs, matches: &ArgMatches, settings: &Settings) {
+    apply!(args, matches, settings, output, cache_dir);
+}
+
+fn detect_repo_root() -> Option<PathBuf> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(PathBuf::from(String::from_utf8(output.stdout).ok()?.trim()))
+}
+
 fn detect_github_repo() -> Result<String> {
     let output = std::process::Command::new("git")
         .args(["config", "--get", "remote.origin.url"])
@@ -153,13 +1240,25 @@ fn detect_github_repo() -> Result<String> {
         .with_context(|| "Failed to execute git command")?;
 
     if !output.status.success() {
-        anyhow::bail!("Failed to detect GitHub repository. Are you in a git repository?");
+        return Err(GhError::InvalidRepoUrl(
+            "Failed to detect GitHub repository. Are you in a git repository?".to_string(),
+        )
+        .into());
     }
 
     let url = String::from_utf8(output.stdout).with_context(|| "Invalid UTF-8 in git output")?;
     parse_github_repo_url(&url)
 }
 
+/// Accepts either `owner/repo` or a GitHub repository URL.
+fn resolve_repository(repo: &str) -> Result<String> {
+    if repo.contains("github.com") {
+        parse_github_repo_url(repo)
+    } else {
+        Ok(repo.to_string())
+    }
+}
+
 fn parse_github_repo_url(url: &str) -> Result<String> {
     let url = url.trim();
 
@@ -174,103 +1273,740 @@ fn parse_github_repo_url(url: &str) -> Result<String> {
             .trim_end_matches(".git")
             .to_string())
     } else {
-        anyhow::bail!("Unsupported repository URL format: {}", url)
+        Err(GhError::InvalidRepoUrl(format!("Unsupported repository URL format: {}", url)).into())
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn fetch_issues(
+    args: &FetchArgs,
+    log: &Logger,
+    report: &mut Report,
     client: &GitHubClient,
     owner: &str,
     repo: &str,
-    output_dir: &Path,
     issue_numbers: Option<&[u32]>,
     state: Option<&str>,
-) -> Result<()> {
-    println!(
-        "\n{} issues from {}/{}...",
-        style("Fetching").cyan().bold(),
-        style(owner).green(),
-        style(repo).green()
+) -> Result<Vec<Change>> {
+    log.verbose(
+        "fetching",
+        format!(
+            "\n{} issues from {}/{}...",
+            style("Fetching").cyan().bold(),
+            style(owner).green(),
+            style(repo).green()
+        ),
+        json!({ "owner": owner, "repo": repo }),
     );
 
-    let args = Args::parse();
-    let format = args
-        .format
-        .parse::<OutputFormat>()
-        .map_err(GhError::InvalidFormat)?;
-
-    let issues = client
+    let mut output = Output::new(&args.render, log)?;
+    let cache = args.render.cache();
+    let output_dir = &args.render.output;
+    let (issues, failures) = client
         .fetch_issues(owner, repo, state, issue_numbers, args.batch_size)
         .await?;
+    log.debug(
+        "issues_listed",
+        format!(
+            "Listed {} issues in {} API requests",
+            issues.len(),
+            client.requests_made()
+        ),
+        json!({ "issues": issues.len(), "api_calls": client.requests_made() }),
+    );
+    for failure in failures {
+        log.warn(
+            "issue_failed",
+            format!(
+                "Failed to fetch issue #{}: {} (This issue might be private or deleted; `ghif doctor` checks your access)",
+                failure.number, failure.error
+            ),
+            json!({ "issue": failure.number, "kind": failure.error.kind() }),
+        );
+        report.failed(failure.number, &failure.error);
+    }
 
-    println!("Found {} issues", style(issues.len()).cyan());
-    let pb = ProgressBar::new(issues.len() as u64);
+    log.info(
+        "found",
+        format!("Found {} issues", style(issues.len()).cyan()),
+        json!({ "issues": issues.len() }),
+    );
+    if args.dry_run {
+        let plan = plan_fetch(args, &output, client, owner, repo, issues).await?;
+        print_plan(log, &plan)?;
+        return Ok(Vec::new());
+    }
+    let pb = log.progress_bar(issues.len() as u64);
     pb.set_style(
         ProgressStyle::default_bar()
             .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} issues")?
             .progress_chars("=>-"),
     );
 
-    for issue in issues {
-        let file_path = get_issue_file_path(output_dir, &issue, format);
+    let listed = issues.iter().map(IssueData::number).collect::<HashSet<_>>();
+    let mut changes = Vec::new();
+    for mut issue in issues {
+        let started = Instant::now();
+        let number = issue.number();
 
-        if args.skip_existing && file_path.exists() {
-            println!(
-                "{} existing issue #{}",
-                style("Skipping").yellow(),
-                style(issue.number()).cyan()
+        if !output.writer.writes_issue_files() {
+            // Tabular formats only need issue metadata, so comments aren't fetched
+            cache.store_issue(&issue)?;
+            pb.inc(1);
+            output.add(issue, &[])?;
+            report.written(number, None, started);
+            continue;
+        }
+
+        let file_path = output_dir.join(output.issue_path(&issue));
+
+        if !args.render.combine && args.skip_existing && file_path.exists() {
+            // Keeps comments cached by an earlier fetch, so render, stats, dedupe and
+            // graph still see every listed issue
+            cache.store_issue(&issue)?;
+            output.remove_stale(number, &file_path)?;
+            log.verbose(
+                "issue_skipped",
+                format!(
+                    "{} existing issue #{}",
+                    style("Skipping").yellow(),
+                    style(number).cyan()
+                ),
+                json!({ "issue": number, "reason": "exists" }),
             );
             pb.inc(1);
+            output.indexed.push(issue);
+            report.skipped(number, "exists");
             continue;
         }
 
-        let comments = client.fetch_comments(owner, repo, issue.number()).await?;
-        save_issue_to_file(output_dir, &issue, &comments, format)?;
+        let mut comments = match client
+            .fetch_comments(owner, repo, number, args.with_minimized_status())
+            .await
+        {
+            Ok(comments) => comments,
+            Err(error) if error.is_issue_specific() => {
+                log.warn(
+                    "issue_failed",
+                    format!("Failed to fetch comments of issue #{}: {}", number, error),
+                    json!({ "issue": number, "kind": error.kind() }),
+                );
+                report.failed(number, &error);
+                pb.inc(1);
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+        log.debug(
+            "comments_fetched",
+            format!(
+                "Fetched {} comments of issue #{} ({} API requests so far)",
+                comments.len(),
+                number,
+                client.requests_made()
+            ),
+            json!({ "issue": number, "comments": comments.len(), "api_calls": client.requests_made() }),
+        );
+        let previous_state = cache
+            .load(number)
+            .ok()
+            .flatten()
+            .map(|cached| cached.issue.state().to_string());
+        cache.store(&issue, &comments, args.with_minimized_status())?;
+        retain_comments(&args.render, &mut comments);
+        if args.render.download_assets {
+            let failures = localize_assets(
+                Some(client),
+                output_dir,
+                &args.render.link_prefix(),
+                &mut issue,
+                &mut comments,
+            )
+            .await?;
+            for failure in failures {
+                let warning = format!(
+                    "Failed to download asset {}: {}",
+                    failure.url, failure.error
+                );
+                log.warn(
+                    "asset_failed",
+                    &warning,
+                    json!({ "issue": number, "url": failure.url, "kind": failure.error.kind() }),
+                );
+                report.warnings.push(warning);
+            }
+        }
+        let had_file = output.existing.contains_key(&number);
+        let kind = ChangeKind::of(had_file, previous_state.as_deref(), issue.state());
+        let title = issue.title().to_string();
+        let path = output.add(issue, &comments)?;
+        if path
+            .as_ref()
+            .is_some_and(|saved| saved.outcome == WriteOutcome::Written)
+        {
+            changes.push(Change {
+                number,
+                title,
+                kind,
+            });
+        }
+        report.written(number, path, started);
         pb.inc(1);
     }
 
-    pb.finish_with_message(format!(
-        "{}",
-        style("All issues downloaded successfully!").green().bold()
-    ));
-    Ok(())
-}
+    pb.finish_and_clear();
+    if let Some(mode) = args.prune {
+        prune(
+            args, log, report, client, &output, owner, repo, &listed, mode,
+        )
+        .await?;
+    }
 
-fn get_issue_file_path(output_dir: &Path, issue: &IssueData, format: OutputFormat) -> PathBuf {
-    let filename = format!(
-        "issue-{}-{}.{}",
-        issue.number(),
-        sanitize_filename(issue.title()),
-        get_file_extension(format)
+    report.removed.append(&mut output.removed);
+    report.files.extend(output.finish()?);
+
+    log.info(
+        "finished",
+        format!(
+            "{}",
+            style("All issues downloaded successfully!").green().bold()
+        ),
+        json!({}),
     );
-    output_dir.join(filename)
+    Ok(changes)
 }
 
-fn save_issue_to_file(
-    output_dir: &Path,
-    issue: &IssueData,
-    comments: &[CommentData],
-    format: OutputFormat,
+/// Reconciles the output directory with the fetched issues: files of issues that
+/// are no longer listed are archived or deleted, along with their cache entries.
+#[allow(clippy::too_many_arguments)]
+async fn prune(
+    args: &FetchArgs,
+    log: &Logger,
+    report: &mut Report,
+    client: &GitHubClient,
+    output: &Output<'_>,
+    owner: &str,
+    repo: &str,
+    listed: &HashSet<u64>,
+    mode: PruneMode,
 ) -> Result<()> {
-    let path = get_issue_file_path(output_dir, issue, format);
-    let writer = get_writer(format);
-    let content = writer.write_issue(issue, comments);
+    let cache = args.render.cache();
+    let unlisted = output.unlisted(listed);
+    if listed.is_empty() && !unlisted.is_empty() {
+        anyhow::bail!(
+            "No issues were listed, but {} has files of {} issues; not pruning them. Check --repository and the token's access",
+            args.render.output.display(),
+            unlisted.len()
+        );
+    }
 
-    let path_display = path.display().to_string();
-    std::fs::write(&path, content)?;
-    println!("Saved issue #{} to {}", issue.number(), path_display);
+    for (number, files) in unlisted {
+        let reason = client.locate_missing_issue(owner, repo, number).await?;
+        if matches!(reason, MissingIssue::Unavailable) && !args.prune_unavailable {
+            let warning = format!(
+                "Kept the files of issue #{}: it wasn't found, which may only mean it can't be read with these credentials; use --prune-unavailable to prune it",
+                number
+            );
+            log.warn("issue_kept", &warning, json!({ "issue": number }));
+            report.warnings.push(warning);
+            continue;
+        }
+        for file in &files {
+            prune_file(&args.render.output, file, mode)
+                .with_context(|| format!("Failed to prune {}", file.display()))?;
+        }
+        cache.remove(number)?;
+
+        let files = files
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect::<Vec<_>>();
+        let action = match mode {
+            PruneMode::Archive => "Archived",
+            PruneMode::Delete => "Deleted",
+        };
+        log.info(
+            "issue_pruned",
+            format!(
+                "{} issue #{} ({}): {}",
+                style(action).yellow(),
+                style(number).cyan(),
+                reason,
+                files.join(", ")
+            ),
+            json!({ "issue": number, "reason": reason, "files": files }),
+        );
+        report.pruned.push(PrunedIssue {
+            number,
+            reason,
+            files,
+            archived: mode == PruneMode::Archive,
+        });
+    }
     Ok(())
 }
 
-fn sanitize_filename(name: &str) -> String {
-    name.to_lowercase()
-        .chars()
-        .map(|c| match c {
-            'a'..='z' | '0'..='9' => c,
-            _ => '-',
+/// Works out what [`fetch_issues`] would do with the listed issues, following the
+/// same skip rules, and the API calls left to make.
+async fn plan_fetch(
+    args: &FetchArgs,
+    output: &Output<'_>,
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    issues: Vec<IssueData>,
+) -> Result<Plan> {
+    let writes_files = output.writer.writes_issue_files();
+    let mut rest_calls = 0;
+    let mut graphql_calls = 0;
+    let mut planned = Vec::new();
+    let listed = issues.iter().map(IssueData::number).collect::<HashSet<_>>();
+
+    for issue in issues {
+        let file_path = args.render.output.join(output.issue_path(&issue));
+        let exists = file_path.exists();
+        let (action, path) = if !writes_files || args.render.combine {
+            (Action::Include, None)
+        } else if args.skip_existing && exists {
+            (Action::Skip, Some(file_path.clone()))
+        } else if exists {
+            (Action::Overwrite, Some(file_path.clone()))
+        } else {
+            (Action::Create, Some(file_path.clone()))
+        };
+
+        if writes_files && action != Action::Skip {
+            rest_calls += plan::comment_requests(&issue);
+            if args.with_minimized_status() {
+                graphql_calls += plan::minimized_requests(&issue);
+            }
+        }
+        planned.push(PlannedIssue {
+            number: issue.number(),
+            title: issue.title().to_string(),
+            action,
+            path: path.map(|path| path.display().to_string()),
+            stale: match action {
+                Action::Include => Vec::new(),
+                _ => output
+                    .stale(issue.number(), &file_path)
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            },
+        });
+    }
+
+    if args.prune.is_some() {
+        for (number, files) in output.unlisted(&listed) {
+            // One request each to find out why the issue is missing
+            rest_calls += 1;
+            planned.push(PlannedIssue {
+                number,
+                title: String::new(),
+                action: Action::Prune,
+                path: None,
+                stale: files
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect(),
+            });
+        }
+    }
+
+    let rate_limit = client.rate_limits().await?;
+    let quota = |rate: &Rate| Quota {
+        remaining: rate.remaining,
+        limit: rate.limit,
+        reset: DateTime::<Utc>::from_timestamp(rate.reset as i64, 0)
+            .map(|reset| reset.to_rfc3339())
+            .unwrap_or_default(),
+    };
+    Ok(Plan {
+        repository: format!("{}/{}", owner, repo),
+        format: args.render.format.clone(),
+        issues: planned,
+        listing_calls: client.requests_made(),
+        rest_calls,
+        graphql_calls,
+        rest_quota: quota(&rate_limit.resources.core),
+        graphql_quota: rate_limit.resources.graphql.as_ref().map(quota),
+    })
+}
+
+/// Prints the plan on stdout, as JSON with `--log-format json`, and warns when it
+/// won't fit the remaining quota.
+fn print_plan(log: &Logger, plan: &Plan) -> Result<()> {
+    if log.is_json() {
+        println!("{}", serde_json::to_string_pretty(plan)?);
+    } else {
+        println!(
+            "{} {} ({})",
+            style("Plan for").cyan().bold(),
+            plan.repository,
+            plan.format
+        );
+        for issue in &plan.issues {
+            let action = match issue.action {
+                Action::Create => style(issue.action.as_str()).green(),
+                Action::Overwrite => style(issue.action.as_str()).yellow(),
+                Action::Skip | Action::Include => style(issue.action.as_str()).dim(),
+                Action::Prune => style(issue.action.as_str()).red(),
+            };
+            println!(
+                "  {:<9} #{:<6} {}",
+                action,
+                issue.number,
+                issue.path.as_deref().unwrap_or(&issue.title)
+            );
+            for stale in &issue.stale {
+                println!("  {:<9} {:<7} {}", style("remove").red(), "", stale);
+            }
+        }
+        println!(
+            "\n{} issues: {} to create, {} to overwrite, {} to skip, {} to include in combined or index files, {} to prune",
+            plan.issues.len(),
+            plan.count(Action::Create),
+            plan.count(Action::Overwrite),
+            plan.count(Action::Skip),
+            plan.count(Action::Include),
+            plan.count(Action::Prune)
+        );
+        let stale = plan.issues.iter().map(|i| i.stale.len()).sum::<usize>();
+        if stale > 0 {
+            println!(
+                "{} stale files left from earlier names would be removed",
+                stale
+            );
+        }
+        println!(
+            "{} {} made to list issues, ~{} more to fetch comments; {}/{} remaining, resets at {}",
+            style("REST API:").cyan().bold(),
+            plan.listing_calls,
+            plan.rest_calls,
+            plan.rest_quota.remaining,
+            plan.rest_quota.limit,
+            plan.rest_quota.reset
+        );
+        if plan.graphql_calls > 0 {
+            let quota = plan.graphql_quota.as_ref().map_or_else(
+                || "unavailable without a token".to_string(),
+                |quota| {
+                    format!(
+                        "{}/{} remaining, resets at {}",
+                        quota.remaining, quota.limit, quota.reset
+                    )
+                },
+            );
+            println!(
+                "{} ~{} queries to find hidden comments; {}",
+                style("GraphQL API:").cyan().bold(),
+                plan.graphql_calls,
+                quota
+            );
+        }
+    }
+
+    if !plan.fits() {
+        log.warn(
+            "over_quota",
+            "The run needs more API calls than remain; it would stop at the rate limit. Wait for the reset, fetch fewer issues with --issues or --state, or use --skip-existing",
+            json!({ "rest_calls": plan.rest_calls, "graphql_calls": plan.graphql_calls }),
+        );
+    }
+    Ok(())
+}
+
+fn retain_comments(args: &RenderArgs, comments: &mut Vec<CommentData>) {
+    comments.retain(|c| {
+        let excluded = (args.exclude_bots && c.is_bot) || (args.exclude_minimized && c.minimized);
+        !excluded
+    });
+}
+
+/// Renders issues with the output options and writes them out; shared by `fetch`
+/// and `render`.
+struct Output<'a> {
+    args: &'a RenderArgs,
+    log: &'a Logger,
+    format: OutputFormat,
+    writer: Box<dyn FormatWriter>,
+    budget: Option<TokenBudget>,
+    rendered: Vec<RenderedIssue>,
+    indexed: Vec<IssueData>,
+    /// Files already in the output directory, by issue number
+    existing: HashMap<u64, Vec<PathBuf>>,
+    /// Stale files removed so far
+    removed: Vec<String>,
+}
+
+impl<'a> Output<'a> {
+    fn new(args: &'a RenderArgs, log: &'a Logger) -> Result<Self> {
+        let format = args
+            .format
+            .parse::<OutputFormat>()
+            .map_err(GhError::InvalidFormat)?;
+
+        if args.chunk_overlap >= args.chunk_size {
+            return Err(GhError::ConfigError(
+                "--chunk-overlap must be smaller than --chunk-size".to_string(),
+            )
+            .into());
+        }
+        let writer = get_writer(
+            format,
+            &WriterOptions {
+                chunk_size: args.chunk_size,
+                chunk_overlap: args.chunk_overlap,
+                dates: DateFormat::new(args.date_format.clone(), args.timezone),
+                link_prefix: args.link_prefix(),
+            },
+        );
+        let budget = args.max_tokens.map(|max_tokens| TokenBudget {
+            max_tokens,
+            keep_comments: args.keep_comments,
+        });
+        let existing = if writer.writes_issue_files() && !args.combine {
+            args.filename_template
+                .find_existing(
+                    &args.output,
+                    get_file_extension(format),
+                    &[ARCHIVE_DIR, "assets"],
+                )
+                .with_context(|| format!("Failed to scan {}", args.output.display()))?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Output {
+            args,
+            log,
+            format,
+            writer,
+            budget,
+            rendered: Vec::new(),
+            indexed: Vec::new(),
+            existing,
+            removed: Vec::new(),
         })
-        .collect::<String>()
-        .replace("--", "-")
-        .trim_matches('-')
-        .to_string()
+    }
+
+    /// The issue's file path, relative to the output directory.
+    fn issue_path(&self, issue: &IssueData) -> PathBuf {
+        self.args
+            .filename_template
+            .path(issue, get_file_extension(self.format))
+    }
+
+    /// Other files for the same issue, written before its title or state changed.
+    fn stale<'b>(&'b self, number: u64, current: &'b Path) -> impl Iterator<Item = &'b PathBuf> {
+        self.existing
+            .get(&number)
+            .into_iter()
+            .flatten()
+            .filter(move |path| path.as_path() != current)
+    }
+
+    /// Files of issues that weren't listed, by issue number.
+    fn unlisted(&self, listed: &HashSet<u64>) -> Vec<(u64, Vec<PathBuf>)> {
+        let mut unlisted = self
+            .existing
+            .iter()
+            .filter(|(number, _)| !listed.contains(number))
+            .map(|(number, files)| (*number, files.clone()))
+            .collect::<Vec<_>>();
+        unlisted.sort_by_key(|(number, _)| *number);
+        unlisted
+    }
+
+    /// Removes the issue's stale files, and directories the template created that
+    /// are left empty.
+    fn remove_stale(&mut self, number: u64, current: &Path) -> Result<()> {
+        let stale = self.stale(number, current).cloned().collect::<Vec<_>>();
+        for path in stale {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove stale file: {}", path.display()))?;
+            remove_empty_dirs(&self.args.output, &path);
+
+            let path = path.display().to_string();
+            self.log.info(
+                "stale_removed",
+                format!(
+                    "{} stale file {} of issue #{}",
+                    style("Removed").yellow(),
+                    path,
+                    style(number).cyan()
+                ),
+                json!({ "issue": number, "path": path }),
+            );
+            self.removed.push(path);
+        }
+        self.existing.remove(&number);
+        Ok(())
+    }
+
+    /// Renders an issue and saves it, or holds it for the combined files. Returns the
+    /// path of the issue's own file, if it got one.
+    fn add(&mut self, issue: IssueData, comments: &[CommentData]) -> Result<Option<SavedFile>> {
+        if !self.writer.writes_issue_files() {
+            self.indexed.push(issue);
+            return Ok(None);
+        }
+
+        let output = match &self.budget {
+            Some(budget) => budget.render(self.writer.as_ref(), &issue, comments),
+            None => RenderedIssue::full(self.writer.as_ref(), &issue, comments),
+        };
+        if let Some(elision) = &output.elision {
+            self.log.verbose(
+                "comments_omitted",
+                format!(
+                    "{} {} comments from issue #{} to fit the token budget",
+                    style("Omitted").yellow(),
+                    elision.omitted,
+                    style(issue.number()).cyan()
+                ),
+                json!({ "issue": issue.number(), "omitted": elision.omitted }),
+            );
+        }
+        if output.over_budget {
+            self.log.warn(
+                "over_budget",
+                format!(
+                    "Issue #{} is still ~{} tokens, over the budget",
+                    issue.number(),
+                    output.tokens
+                ),
+                json!({ "issue": issue.number(), "tokens": output.tokens }),
+            );
+        }
+
+        let path = if self.args.combine {
+            self.rendered.push(output);
+            None
+        } else {
+            let path = self.args.output.join(self.issue_path(&issue));
+            let saved = save_issue_to_file(self.log, &path, issue.number(), &output.content)?;
+            self.remove_stale(issue.number(), &path)?;
+            Some(saved)
+        };
+        self.indexed.push(issue);
+        Ok(path)
+    }
+
+    /// Writes the index and, with `--combine`, the combined files. Returns the files
+    /// written.
+    fn finish(self) -> Result<Vec<String>> {
+        let output_dir = &self.args.output;
+        let mut files = Vec::new();
+        let entries = self
+            .indexed
+            .iter()
+            .map(|issue| IndexEntry {
+                issue,
+                path: (!self.args.combine).then(|| self.issue_path(issue).display().to_string()),
+            })
+            .collect::<Vec<_>>();
+        if let Some(index) = self.writer.write_index(&entries) {
+            let file = get_index_file_name(self.format);
+            if write_if_changed(&output_dir.join(&file), index)? == WriteOutcome::Written {
+                self.log.info(
+                    "index_saved",
+                    format!("Saved index of {} issues to {}", entries.len(), file),
+                    json!({ "issues": entries.len(), "path": file }),
+                );
+            }
+            files.push(file);
+        }
+
+        if self.args.combine {
+            files.extend(save_combined_files(
+                self.log,
+                output_dir,
+                self.writer.as_ref(),
+                &self.rendered,
+                self.args.max_tokens,
+                self.format,
+            )?);
+        }
+        Ok(files)
+    }
+}
+
+fn save_issue_to_file(log: &Logger, path: &Path, number: u64, content: &str) -> Result<SavedFile> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let path_display = path.display().to_string();
+    let outcome = write_if_changed(path, content)?;
+    match outcome {
+        WriteOutcome::Written => log.verbose(
+            "issue_saved",
+            format!("Saved issue #{} to {}", number, path_display),
+            json!({ "issue": number, "path": path_display }),
+        ),
+        WriteOutcome::Unchanged => log.verbose(
+            "issue_unchanged",
+            format!("Issue #{} is unchanged in {}", number, path_display),
+            json!({ "issue": number, "path": path_display }),
+        ),
+    }
+    Ok(SavedFile {
+        path: path_display,
+        outcome,
+    })
+}
+
+/// Writes the combined files and their manifest, returning their names.
+fn save_combined_files(
+    log: &Logger,
+    output_dir: &Path,
+    writer: &dyn FormatWriter,
+    rendered: &[RenderedIssue],
+    max_tokens: Option<usize>,
+    format: OutputFormat,
+) -> Result<Vec<String>> {
+    let mut manifest = Vec::new();
+
+    for (i, indices) in pack_chunks(rendered, max_tokens).into_iter().enumerate() {
+        let file = format!("issues-{:03}.{}", i + 1, get_file_extension(format));
+        let parts = indices
+            .iter()
+            .map(|&i| rendered[i].content.as_str())
+            .collect::<Vec<_>>();
+        if write_if_changed(&output_dir.join(&file), writer.combine(&parts))?
+            == WriteOutcome::Written
+        {
+            log.info(
+                "combined_saved",
+                format!("Saved {} issues to {}", indices.len(), file),
+                json!({ "issues": indices.len(), "path": file }),
+            );
+        }
+
+        manifest.push(Chunk {
+            file,
+            tokens: indices.iter().map(|&i| rendered[i].tokens).sum(),
+            issues: indices
+                .iter()
+                .map(|&i| ChunkEntry {
+                    number: rendered[i].number,
+                    tokens: rendered[i].tokens,
+                    elision: rendered[i].elision.clone(),
+                    over_budget: rendered[i].over_budget,
+                })
+                .collect(),
+        });
+    }
+
+    let mut files = manifest
+        .iter()
+        .map(|chunk| chunk.file.clone())
+        .collect::<Vec<_>>();
+    write_if_changed(
+        &output_dir.join("issues-manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    files.push("issues-manifest.json".to_string());
+    Ok(files)
 }
@@ -0,0 +1,432 @@
+use crate::github::IssueData;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The file naming ghif has always used, e.g. `issue-12-crash-on-start.md`.
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "issue-{number}-{slug}";
+
+/// Slugs are cut to this many bytes, so CJK titles (3 bytes a character) stay well
+/// inside the usual 255-byte file name limit.
+const MAX_SLUG_BYTES: usize = 96;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Number,
+    Slug,
+    State,
+    Year,
+    Month,
+    Day,
+    Author,
+}
+
+impl Part {
+    fn placeholder(name: &str) -> Option<Self> {
+        match name {
+            "number" => Some(Part::Number),
+            "slug" => Some(Part::Slug),
+            "state" => Some(Part::State),
+            "year" => Some(Part::Year),
+            "month" => Some(Part::Month),
+            "day" => Some(Part::Day),
+            "author" => Some(Part::Author),
+            _ => None,
+        }
+    }
+}
+
+/// Where each issue's file goes under the output directory, e.g.
+/// `{state}/{number}-{slug}`. `/` separates directories and the extension of the
+/// output format is appended.
+///
+/// Placeholders: `{number}`, `{slug}` (the title, slugged), `{state}`, `{year}`,
+/// `{month}` and `{day}` (of creation, in UTC) and `{author}`. `{number}` must be part
+/// of the file name, which is what lets files left behind under an old name (after a
+/// title change, say) be found again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate {
+    segments: Vec<Vec<Part>>,
+}
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        DEFAULT_FILENAME_TEMPLATE
+            .parse()
+            .expect("default template is valid")
+    }
+}
+
+impl FromStr for FilenameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with('/') || s.contains('\\') {
+            return Err(format!(
+                "Filename template must be a relative path using '/': {}",
+                s
+            ));
+        }
+
+        let mut segments = Vec::new();
+        for segment in s.split('/') {
+            if segment.is_empty() || segment == "." || segment == ".." {
+                return Err(format!("Invalid path segment in filename template: {}", s));
+            }
+            segments.push(parse_segment(segment)?);
+        }
+
+        let file_name = segments.last().expect("split yields a segment");
+        if !file_name.contains(&Part::Number) {
+            return Err(format!(
+                "Filename template must contain {{number}} in the file name: {}",
+                s
+            ));
+        }
+        Ok(FilenameTemplate { segments })
+    }
+}
+
+fn parse_segment(segment: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut rest = segment;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            parts.push(Part::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in filename template: {}", segment))?;
+        let name = &rest[start + 1..start + end];
+        parts
+            .push(Part::placeholder(name).ok_or_else(|| {
+                format!("Unknown placeholder in filename template: {{{}}}", name)
+            })?);
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+impl FilenameTemplate {
+    /// The issue's file path, relative to the output directory.
+    pub fn path(&self, issue: &IssueData, extension: &str) -> PathBuf {
+        let mut path = PathBuf::new();
+        let last = self.segments.len() - 1;
+        for (i, segment) in self.segments.iter().enumerate() {
+            let mut name = segment
+                .iter()
+                .map(|part| render_part(part, issue))
+                .collect::<String>();
+            if i == last {
+                name.push('.');
+                name.push_str(extension);
+            }
+            path.push(name);
+        }
+        path
+    }
+
+    /// What links from an issue file need in front to reach the output directory, e.g.
+    /// `../` for `{state}/{number}`.
+    pub fn link_prefix(&self) -> String {
+        "../".repeat(self.segments.len() - 1)
+    }
+
+    /// Finds the files under `dir` that this template could have written, by issue
+    /// number. Placeholders other than `{number}` match anything, so a file written
+    /// before the title or state changed is found too. Hidden entries, and the
+    /// top-level entries named in `ignore`, are skipped.
+    pub fn find_existing(
+        &self,
+        dir: &Path,
+        extension: &str,
+        ignore: &[&str],
+    ) -> std::io::Result<HashMap<u64, Vec<PathBuf>>> {
+        let mut found = HashMap::new();
+        self.scan(dir, 0, extension, ignore, &mut found)?;
+        Ok(found)
+    }
+
+    fn scan(
+        &self,
+        dir: &Path,
+        depth: usize,
+        extension: &str,
+        ignore: &[&str],
+        found: &mut HashMap<u64, Vec<PathBuf>>,
+    ) -> std::io::Result<()> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let is_file_name = depth == self.segments.len() - 1;
+
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if name.starts_with('.') || (depth == 0 && ignore.contains(&name)) {
+                continue;
+            }
+
+            let file_type = entry.file_type()?;
+            if !is_file_name {
+                if file_type.is_dir() && matches(&self.segments[depth], name, &mut None) {
+                    self.scan(&entry.path(), depth + 1, extension, &[], found)?;
+                }
+                continue;
+            }
+
+            let Some(stem) = name
+                .strip_suffix(extension)
+                .and_then(|s| s.strip_suffix('.'))
+            else {
+                continue;
+            };
+            let mut number = None;
+            if file_type.is_file() && matches(&self.segments[depth], stem, &mut number) {
+                if let Some(number) = number {
+                    found.entry(number).or_default().push(entry.path());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn render_part(part: &Part, issue: &IssueData) -> String {
+    match part {
+        Part::Literal(text) => text.clone(),
+        Part::Number => issue.number().to_string(),
+        Part::Slug => {
+            let slug = slugify(issue.title());
+            if slug.is_empty() {
+                "untitled".to_string()
+            } else {
+                slug
+            }
+        }
+        Part::State => issue.state().to_string(),
+        Part::Year => issue.created_at().format("%Y").to_string(),
+        Part::Month => issue.created_at().format("%m").to_string(),
+        Part::Day => issue.created_at().format("%d").to_string(),
+        Part::Author => slugify(issue.user()),
+    }
+}
+
+/// Whether `name` could be `parts` rendered for some issue, capturing the number.
+/// `{number}` takes every digit at its position, so `issue-12` never matches issue
+/// 123; other placeholders match any text, including none.
+fn matches(parts: &[Part], name: &str, number: &mut Option<u64>) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return name.is_empty();
+    };
+    match part {
+        Part::Literal(text) => name
+            .strip_prefix(text.as_str())
+            .is_some_and(|name| matches(rest, name, number)),
+        Part::Number => {
+            let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                return false;
+            }
+            let Ok(value) = name[..digits].parse() else {
+                return false;
+            };
+            if number.is_some_and(|n| n != value) {
+                return false;
+            }
+            *number = Some(value);
+            matches(rest, &name[digits..], number)
+        }
+        _ => name
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([name.len()])
+            .any(|i| {
+                let mut captured = *number;
+                let found = matches(rest, &name[i..], &mut captured);
+                if found {
+                    *number = captured;
+                }
+                found
+            }),
+    }
+}
+
+/// Lowercases `text` and joins its words with `-`. Letters and digits of any script
+/// are kept, so `クラッシュする` stays readable rather than disappearing.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if slug.len() + c.len_utf8() > MAX_SLUG_BYTES {
+                break;
+            }
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn issue(number: u64, title: &str, state: &str) -> IssueData {
+        serde_json::from_value(json!({
+            "number": number, "title": title, "state": state, "body": null,
+            "labels": [], "created_at": "2024-03-07T10:00:00Z",
+            "updated_at": "2024-03-08T00:00:00Z", "closed_at": null, "assignees": [],
+            "user": "Octo_Cat", "html_url": "https://github.com/owner/repo/issues/1",
+            "comments_url": null, "comment_count": 0, "state_reason": null,
+            "closed_by": null, "milestone": null, "locked": false,
+            "active_lock_reason": null, "author_association": "NONE"
+        }))
+        .unwrap()
+    }
+
+    fn template(s: &str) -> FilenameTemplate {
+        s.parse().unwrap()
+    }
+
+    fn number_of(template: &str, name: &str) -> Option<u64> {
+        let template = self::template(template);
+        let mut number = None;
+        matches(template.segments.last().unwrap(), name, &mut number)
+            .then_some(number)
+            .flatten()
+    }
+
+    #[test]
+    fn slugifies_titles() {
+        assert_eq!(slugify("App crashes on start!"), "app-crashes-on-start");
+        assert_eq!(slugify("  [bug] -- foo/bar  "), "bug-foo-bar");
+        assert_eq!(slugify("Панель не загружается"), "панель-не-загружается");
+        assert_eq!(slugify("クラッシュする"), "クラッシュする");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn cuts_long_slugs_on_character_boundaries() {
+        let slug = slugify(&"あ".repeat(100));
+        assert!(slug.len() <= MAX_SLUG_BYTES);
+        assert_eq!(slug, "あ".repeat(MAX_SLUG_BYTES / 3));
+        assert!(!slugify(&"word ".repeat(40)).ends_with('-'));
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for s in [
+            "/abs/{number}",
+            "a\\{number}",
+            "{state}//{number}",
+            "../{number}",
+            "{number",
+            "{title}-{number}",
+            "{number}/{slug}",
+            "{slug}",
+        ] {
+            assert!(s.parse::<FilenameTemplate>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn renders_paths() {
+        let issue = issue(12, "Crash on start", "open");
+        assert_eq!(
+            FilenameTemplate::default().path(&issue, "md"),
+            PathBuf::from("issue-12-crash-on-start.md")
+        );
+        assert_eq!(
+            template("{state}/{year}/{month}-{day}/{number}-{author}").path(&issue, "xml"),
+            PathBuf::from("open/2024/03-07/12-octo-cat.xml")
+        );
+        assert_eq!(
+            template("{number}-{slug}")
+                .path(&self::issue(3, "???", "open"), "md")
+                .to_str(),
+            Some("3-untitled.md")
+        );
+    }
+
+    #[test]
+    fn link_prefix_climbs_out_of_template_directories() {
+        assert_eq!(FilenameTemplate::default().link_prefix(), "");
+        assert_eq!(template("{state}/{year}/{number}").link_prefix(), "../../");
+    }
+
+    #[test]
+    fn matches_files_written_under_old_titles() {
+        assert_eq!(
+            number_of("issue-{number}-{slug}", "issue-12-old-title"),
+            Some(12)
+        );
+        assert_eq!(number_of("issue-{number}-{slug}", "issue-12-"), Some(12));
+        assert_eq!(
+            number_of("{slug}-{number}", "crash-2-on-start-45"),
+            Some(45)
+        );
+        assert_eq!(number_of("{number}", "0012"), Some(12));
+    }
+
+    #[test]
+    fn number_takes_every_digit() {
+        assert_eq!(number_of("issue-{number}", "issue-123"), Some(123));
+        assert_eq!(number_of("issue-{number}-{slug}", "issue-12x"), None);
+        assert_eq!(number_of("issue-{number}", "issue-"), None);
+        assert_eq!(number_of("issue-{number}", "notes"), None);
+    }
+
+    #[test]
+    fn repeated_numbers_must_agree() {
+        assert_eq!(number_of("{number}-{slug}-{number}", "7-a-7"), Some(7));
+        assert_eq!(number_of("{number}-{slug}-{number}", "7-a-8"), None);
+    }
+
+    #[test]
+    fn finds_existing_files_across_template_directories() {
+        let dir = std::env::temp_dir().join(format!("ghif-naming-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for path in [
+            "open/12-new-title.md",
+            "closed/12-old-title.md",
+            "open/123-other.md",
+            "open/12-new-title.xml",
+            "open/.12-hidden.md",
+            "archive/open/5-archived.md",
+            "notes.md",
+        ] {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let found = template("{state}/{number}-{slug}")
+            .find_existing(&dir, "md", &["archive"])
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut twelve = found[&12].clone();
+        twelve.sort();
+        assert_eq!(
+            twelve,
+            [
+                dir.join("closed/12-old-title.md"),
+                dir.join("open/12-new-title.md")
+            ]
+        );
+        assert_eq!(found[&123], [dir.join("open/123-other.md")]);
+        assert_eq!(found.len(), 2);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::github::IssueData;
+use serde::Serialize;
+
+/// Comments are listed 100 per page, over both the REST and the GraphQL API.
+const COMMENTS_PER_PAGE: usize = 100;
+
+/// What a fetch would do with an issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Write a new file
+    Create,
+    /// Replace an existing file
+    Overwrite,
+    /// Leave the existing file alone (`--skip-existing`)
+    Skip,
+    /// Only add to the combined files or the index
+    Include,
+    /// Archive or delete the files of an issue that's no longer listed (`--prune`)
+    Prune,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Create => "create",
+            Action::Overwrite => "overwrite",
+            Action::Skip => "skip",
+            Action::Include => "include",
+            Action::Prune => "prune",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedIssue {
+    pub number: u64,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    pub action: Action,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Files for the same issue under an earlier name, removed by the fetch; for
+    /// `Prune`, the files pruned
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stale: Vec<String>,
+}
+
+/// Remaining quota of a rate limit bucket.
+#[derive(Debug, Serialize)]
+pub struct Quota {
+    pub remaining: usize,
+    pub limit: usize,
+    pub reset: String,
+}
+
+/// What `--dry-run` reports: the issues a fetch would touch, and the API calls it
+/// would need against the remaining rate limit.
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    pub repository: String,
+    pub format: String,
+    pub issues: Vec<PlannedIssue>,
+    /// REST calls already made to list the issues
+    pub listing_calls: usize,
+    /// REST calls still needed, to fetch comments
+    pub rest_calls: usize,
+    /// GraphQL queries still needed, to find hidden comments
+    pub graphql_calls: usize,
+    pub rest_quota: Quota,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graphql_quota: Option<Quota>,
+}
+
+impl Plan {
+    pub fn count(&self, action: Action) -> usize {
+        self.issues.iter().filter(|i| i.action == action).count()
+    }
+
+    /// Whether the remaining calls fit the remaining quota of both APIs.
+    pub fn fits(&self) -> bool {
+        self.rest_calls <= self.rest_quota.remaining
+            && self
+                .graphql_quota
+                .as_ref()
+                .map_or(self.graphql_calls == 0, |quota| {
+                    self.graphql_calls <= quota.remaining
+                })
+    }
+}
+
+/// REST calls needed to fetch an issue's comments: one per page, and at least one
+/// even when the issue has none.
+pub fn comment_requests(issue: &IssueData) -> usize {
+    (issue.comment_count() as usize)
+        .div_ceil(COMMENTS_PER_PAGE)
+        .max(1)
+}
+
+/// GraphQL queries needed to find an issue's hidden comments; issues without
+/// comments aren't queried.
+pub fn minimized_requests(issue: &IssueData) -> usize {
+    (issue.comment_count() as usize).div_ceil(COMMENTS_PER_PAGE)
+}
@@ -0,0 +1,70 @@
+use crate::github::MissingIssue;
+use serde::Serialize;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Directory under the output directory that `--prune` moves stale files into.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// What `--prune` does with files of issues that are no longer fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneMode {
+    /// Move them into `archive/`, keeping their relative path
+    Archive,
+    Delete,
+}
+
+impl FromStr for PruneMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "archive" => Ok(PruneMode::Archive),
+            "delete" => Ok(PruneMode::Delete),
+            _ => Err(format!(
+                "Unsupported prune mode: {} (use archive or delete)",
+                s
+            )),
+        }
+    }
+}
+
+/// An issue whose files were pruned, and why it was missing.
+#[derive(Debug, Serialize)]
+pub struct PrunedIssue {
+    pub number: u64,
+    #[serde(flatten)]
+    pub reason: MissingIssue,
+    /// The files as they were before pruning
+    pub files: Vec<String>,
+    pub archived: bool,
+}
+
+/// Moves `path` (under `root`) to the same relative path under `root/archive/`, or
+/// deletes it. Directories left empty are removed as well.
+pub fn prune_file(root: &Path, path: &Path, mode: PruneMode) -> std::io::Result<()> {
+    match mode {
+        PruneMode::Archive => {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let target = root.join(ARCHIVE_DIR).join(relative);
+            if let Some(dir) = target.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::rename(path, target)?;
+        }
+        PruneMode::Delete => std::fs::remove_file(path)?,
+    }
+    remove_empty_dirs(root, path);
+    Ok(())
+}
+
+/// Removes the directories above `path` that are left empty, up to `root`.
+pub fn remove_empty_dirs(root: &Path, path: &Path) {
+    let mut dir = path.parent();
+    while let Some(parent) = dir.filter(|dir| *dir != root) {
+        if std::fs::remove_dir(parent).is_err() {
+            break;
+        }
+        dir = parent.parent();
+    }
+}
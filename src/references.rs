@@ -0,0 +1,434 @@
+use crate::github::{CommentData, IssueData};
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::Serialize;
+
+/// An issue, pull request or commit mentioned in an issue thread, always with the
+/// repository it's in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Reference {
+    /// An issue or pull request; the two share numbers, and GitHub redirects issue
+    /// URLs of pull requests
+    Issue {
+        repository: String,
+        number: u64,
+    },
+    Commit {
+        repository: String,
+        sha: String,
+    },
+}
+
+impl Reference {
+    pub fn repository(&self) -> &str {
+        match self {
+            Reference::Issue { repository, .. } | Reference::Commit { repository, .. } => {
+                repository
+            }
+        }
+    }
+
+    /// A stable identifier across repositories, e.g. `owner/repo#12` or
+    /// `owner/repo@<sha>`.
+    pub fn id(&self) -> String {
+        match self {
+            Reference::Issue { repository, number } => format!("{}#{}", repository, number),
+            Reference::Commit { repository, sha } => format!("{}@{}", repository, sha),
+        }
+    }
+
+    pub fn url(&self, host: &str) -> String {
+        match self {
+            Reference::Issue { repository, number } => {
+                format!("https://{}/{}/issues/{}", host, repository, number)
+            }
+            Reference::Commit { repository, sha } => {
+                format!("https://{}/{}/commit/{}", host, repository, sha)
+            }
+        }
+    }
+
+    /// How GitHub shows the reference from within `repository`: `#12` and short
+    /// SHAs for its own, `owner/repo#12` and `owner/repo@abc1234` for others.
+    pub fn label(&self, repository: &str) -> String {
+        let prefix = if self.repository() == repository {
+            ""
+        } else {
+            self.repository()
+        };
+        match self {
+            Reference::Issue { number, .. } => format!("{}#{}", prefix, number),
+            Reference::Commit { sha, .. } if prefix.is_empty() => short(sha).to_string(),
+            Reference::Commit { sha, .. } => format!("{}@{}", prefix, short(sha)),
+        }
+    }
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// The host and `owner/repo` of an issue, from its URL; references without a
+/// repository are relative to it.
+#[derive(Debug, Clone)]
+pub struct Origin {
+    pub host: String,
+    pub repository: String,
+}
+
+impl Origin {
+    pub fn of(issue: &IssueData) -> Option<Origin> {
+        let rest = issue
+            .html_url()
+            .strip_prefix("https://")
+            .or_else(|| issue.html_url().strip_prefix("http://"))?;
+        let mut parts = rest.split('/');
+        let host = parts.next()?;
+        let (owner, repo) = (parts.next()?, parts.next()?);
+        Some(Origin {
+            host: host.to_string(),
+            repository: format!("{}/{}", owner, repo),
+        })
+    }
+}
+
+/// References in an issue's description and comments, in order of first mention,
+/// leaving out the issue itself. Code blocks and inline code are skipped, as GitHub
+/// doesn't link references in them.
+pub fn find_references(issue: &IssueData, comments: &[CommentData]) -> Vec<Reference> {
+    let Some(origin) = Origin::of(issue) else {
+        return Vec::new();
+    };
+    let itself = Reference::Issue {
+        repository: origin.repository.clone(),
+        number: issue.number(),
+    };
+
+    let mut references = Vec::new();
+    let texts = std::iter::once(issue.body().unwrap_or_default())
+        .chain(comments.iter().map(|comment| comment.body.as_str()));
+    for text in texts {
+        for reference in references_in(text, &origin) {
+            if reference != itself && !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+    }
+    references
+}
+
+/// References in a Markdown text: `#12`, `GH-12`, `owner/repo#12`, issue, pull
+/// request and commit URLs, `owner/repo@sha` and bare commit SHAs.
+fn references_in(markdown: &str, origin: &Origin) -> Vec<Reference> {
+    // The parser splits text around characters that might start inline markup, so
+    // consecutive text is joined before it's split into words
+    let mut text = String::new();
+    let mut in_code_block = false;
+    let mut references = Vec::new();
+    let mut scan = |text: &mut String| {
+        references.extend(
+            text.split_whitespace()
+                .filter_map(|word| parse_word(trim_punctuation(word), origin)),
+        );
+        text.clear();
+    };
+
+    for event in Parser::new(markdown) {
+        let line_break = matches!(event, Event::SoftBreak | Event::HardBreak);
+        match event {
+            Event::Text(fragment) if !in_code_block => {
+                text.push_str(&fragment);
+                continue;
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Start(Tag::Link(_, dest, _)) => {
+                text.push(' ');
+                text.push_str(&dest);
+            }
+            _ => {}
+        }
+        text.push(' ');
+        if !line_break {
+            scan(&mut text);
+        }
+    }
+    scan(&mut text);
+    references
+}
+
+fn trim_punctuation(word: &str) -> &str {
+    word.trim_start_matches(['(', '[', '{', '<', '"', '\''])
+        .trim_end_matches([')', ']', '}', '>', '"', '\'', '.', ',', ':', ';', '!', '?'])
+}
+
+fn parse_word(word: &str, origin: &Origin) -> Option<Reference> {
+    if let Some(rest) = word
+        .strip_prefix("https://")
+        .or_else(|| word.strip_prefix("http://"))
+    {
+        return parse_url(rest, origin);
+    }
+
+    if let Some(number) = word.strip_prefix('#').or_else(|| word.strip_prefix("GH-")) {
+        return Some(Reference::Issue {
+            repository: origin.repository.clone(),
+            number: parse_number(number)?,
+        });
+    }
+
+    if let Some((repository, number)) = word.split_once('#') {
+        return Some(Reference::Issue {
+            repository: parse_repository(repository)?,
+            number: parse_number(number)?,
+        });
+    }
+    if let Some((repository, sha)) = word.split_once('@') {
+        return Some(Reference::Commit {
+            repository: parse_repository(repository)?,
+            sha: parse_sha(sha)?,
+        });
+    }
+
+    Some(Reference::Commit {
+        repository: origin.repository.clone(),
+        sha: parse_sha(word)?,
+    })
+}
+
+/// An issue, pull request or commit URL on GitHub or the issue's own host, without
+/// the scheme. Anchors (e.g. to a comment) and query strings are ignored.
+fn parse_url(url: &str, origin: &Origin) -> Option<Reference> {
+    let url = url.split(['#', '?']).next()?;
+    let mut parts = url.split('/');
+    let host = parts.next()?;
+    if host != "github.com" && host != origin.host {
+        return None;
+    }
+    let repository = parse_repository(&format!("{}/{}", parts.next()?, parts.next()?))?;
+    let reference = match (parts.next()?, parts.next()?) {
+        ("issues" | "pull", number) => Reference::Issue {
+            repository,
+            number: parse_number(number)?,
+        },
+        ("commit", sha) => Reference::Commit {
+            repository,
+            sha: parse_sha(sha)?,
+        },
+        _ => return None,
+    };
+    Some(reference)
+}
+
+fn parse_number(number: &str) -> Option<u64> {
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok().filter(|&n| n > 0)
+}
+
+/// `owner/repo`, as GitHub allows them: letters, digits and `-` in owners, plus `.`
+/// and `_` in repository names.
+fn parse_repository(repository: &str) -> Option<String> {
+    let (owner, repo) = repository.split_once('/')?;
+    let valid_owner = !owner.is_empty()
+        && owner
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-');
+    let valid_repo = !repo.is_empty()
+        && repo
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_'));
+    (valid_owner && valid_repo).then(|| repository.to_string())
+}
+
+/// 7 to 40 hex digits. Words of only digits or only letters (e.g. "1234567",
+/// "defaced") are too often not commits, so a SHA needs both.
+fn parse_sha(sha: &str) -> Option<String> {
+    let valid = (7..=40).contains(&sha.len())
+        && sha.bytes().all(|b| b.is_ascii_hexdigit())
+        && sha.bytes().any(|b| b.is_ascii_digit())
+        && sha.bytes().any(|b| b.is_ascii_alphabetic());
+    valid.then(|| sha.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn origin() -> Origin {
+        Origin {
+            host: "github.com".to_string(),
+            repository: "owner/repo".to_string(),
+        }
+    }
+
+    fn issue(number: u64, body: &str) -> IssueData {
+        serde_json::from_value(json!({
+            "number": number, "title": "Crash", "state": "open", "body": body,
+            "labels": [], "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z", "closed_at": null, "assignees": [],
+            "user": "octocat",
+            "html_url": format!("https://github.com/owner/repo/issues/{}", number),
+            "comments_url": null, "comment_count": 0, "state_reason": null,
+            "closed_by": null, "milestone": null, "locked": false,
+            "active_lock_reason": null, "author_association": "NONE"
+        }))
+        .unwrap()
+    }
+
+    fn issue_ref(repository: &str, number: u64) -> Reference {
+        Reference::Issue {
+            repository: repository.to_string(),
+            number,
+        }
+    }
+
+    fn commit_ref(repository: &str, sha: &str) -> Reference {
+        Reference::Commit {
+            repository: repository.to_string(),
+            sha: sha.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_issue_references() {
+        let origin = origin();
+        assert_eq!(
+            parse_word("#12", &origin),
+            Some(issue_ref("owner/repo", 12))
+        );
+        assert_eq!(
+            parse_word("GH-12", &origin),
+            Some(issue_ref("owner/repo", 12))
+        );
+        assert_eq!(
+            parse_word("other-org/tool.rs#7", &origin),
+            Some(issue_ref("other-org/tool.rs", 7))
+        );
+    }
+
+    #[test]
+    fn rejects_words_that_are_not_references() {
+        let origin = origin();
+        for word in [
+            "#",
+            "#0",
+            "#12a",
+            "#-1",
+            "C#",
+            "my_org/repo#1",
+            "/repo#1",
+            "owner/#1",
+            "email@example.com",
+            "1234567",
+            "defaced",
+            "abc123",
+            "abcdef0123g",
+        ] {
+            assert_eq!(parse_word(word, &origin), None, "{:?}", word);
+        }
+    }
+
+    #[test]
+    fn parses_commit_references() {
+        let origin = origin();
+        assert_eq!(
+            parse_word("A1B2C3D", &origin),
+            Some(commit_ref("owner/repo", "a1b2c3d"))
+        );
+        assert_eq!(
+            parse_word("other/repo@a1b2c3d4", &origin),
+            Some(commit_ref("other/repo", "a1b2c3d4"))
+        );
+        assert_eq!(parse_word(&"a1".repeat(21), &origin), None);
+    }
+
+    #[test]
+    fn parses_urls() {
+        let origin = origin();
+        assert_eq!(
+            parse_word(
+                "https://github.com/other/repo/issues/5#issuecomment-1",
+                &origin
+            ),
+            Some(issue_ref("other/repo", 5))
+        );
+        assert_eq!(
+            parse_word("https://github.com/owner/repo/pull/9?diff=split", &origin),
+            Some(issue_ref("owner/repo", 9))
+        );
+        assert_eq!(
+            parse_word("http://github.com/owner/repo/commit/0123abcd", &origin),
+            Some(commit_ref("owner/repo", "0123abcd"))
+        );
+        assert_eq!(
+            parse_word("https://github.com/owner/repo/wiki/5", &origin),
+            None
+        );
+        assert_eq!(
+            parse_word("https://gitlab.com/owner/repo/issues/5", &origin),
+            None
+        );
+    }
+
+    #[test]
+    fn accepts_urls_on_the_issue_host() {
+        let origin = Origin {
+            host: "github.example.com".to_string(),
+            repository: "owner/repo".to_string(),
+        };
+        assert_eq!(
+            parse_word("https://github.example.com/team/app/issues/3", &origin),
+            Some(issue_ref("team/app", 3))
+        );
+    }
+
+    #[test]
+    fn trims_surrounding_punctuation() {
+        assert_eq!(trim_punctuation("(#12)."), "#12");
+        assert_eq!(trim_punctuation("\"owner/repo#3\","), "owner/repo#3");
+    }
+
+    #[test]
+    fn finds_references_outside_code() {
+        let body = "Same as #3 and (#4).\n\n```\nsee #5\n```\n\nNot `#6` either, but [this](https://github.com/owner/repo/issues/7)\nand #3 again, #12 itself.";
+        let references = find_references(&issue(12, body), &[]);
+        assert_eq!(
+            references,
+            [
+                issue_ref("owner/repo", 3),
+                issue_ref("owner/repo", 4),
+                issue_ref("owner/repo", 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_text_split_by_the_markdown_parser() {
+        let references = find_references(&issue(1, "Fixed in owner/repo#2_ and *#3*"), &[]);
+        assert_eq!(
+            references,
+            [issue_ref("owner/repo", 3)],
+            "`#2_` isn't a reference but `*#3*` is"
+        );
+    }
+
+    #[test]
+    fn labels_references_relative_to_the_repository() {
+        assert_eq!(issue_ref("owner/repo", 3).label("owner/repo"), "#3");
+        assert_eq!(
+            issue_ref("other/repo", 3).label("owner/repo"),
+            "other/repo#3"
+        );
+        assert_eq!(
+            commit_ref("owner/repo", "0123456789abcdef").label("owner/repo"),
+            "0123456"
+        );
+        assert_eq!(
+            commit_ref("other/repo", "0123456789abcdef").label("owner/repo"),
+            "other/repo@0123456"
+        );
+    }
+}
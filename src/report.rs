@@ -0,0 +1,185 @@
+use crate::error::GhError;
+use crate::files::{SavedFile, WriteOutcome};
+use crate::prune::PrunedIssue;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// Every issue was written or deliberately skipped
+    Success,
+    /// The run finished, but some issues failed
+    Partial,
+    /// The run stopped early
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Written,
+    /// Rendered, but identical to the file already on disk
+    Unchanged,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueOutcome {
+    pub number: u64,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Machine-readable error kind for failures, e.g. `not_found`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Counts {
+    pub found: usize,
+    pub written: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportError {
+    pub kind: &'static str,
+    pub message: String,
+    pub exit_code: u8,
+}
+
+/// What a run did, written with `--report` so pipelines can alert on partial
+/// failures without scraping the console output.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub command: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    pub format: String,
+    pub status: RunStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ReportError>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub api_calls: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit_remaining: Option<usize>,
+    pub counts: Counts,
+    pub issues: Vec<IssueOutcome>,
+    /// Files written besides the per-issue ones: indexes, combined files, manifests
+    pub files: Vec<String>,
+    /// Files removed, such as an issue's file under its old title
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+    /// Issues no longer fetched whose files `--prune` archived or deleted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pruned: Vec<PrunedIssue>,
+    /// The `--git-commit` commit, if anything changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    pub warnings: Vec<String>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl Report {
+    pub fn new(command: &'static str, format: &str) -> Self {
+        let now = Utc::now();
+        Report {
+            command,
+            repository: None,
+            format: format.to_string(),
+            status: RunStatus::Success,
+            error: None,
+            started_at: now,
+            finished_at: now,
+            duration_ms: 0,
+            api_calls: 0,
+            rate_limit_remaining: None,
+            counts: Counts::default(),
+            issues: Vec::new(),
+            files: Vec::new(),
+            removed: Vec::new(),
+            pruned: Vec::new(),
+            commit: None,
+            warnings: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Records a rendered issue, with the file it was saved to if it got its own.
+    pub fn written(&mut self, number: u64, file: Option<SavedFile>, started: Instant) {
+        let outcome = match file {
+            Some(SavedFile {
+                outcome: WriteOutcome::Unchanged,
+                ..
+            }) => Outcome::Unchanged,
+            _ => Outcome::Written,
+        };
+        self.issues.push(IssueOutcome {
+            number,
+            outcome,
+            path: file.map(|file| file.path),
+            reason: None,
+            error_kind: None,
+            duration_ms: Some(started.elapsed().as_millis() as u64),
+        });
+    }
+
+    pub fn skipped(&mut self, number: u64, reason: &str) {
+        self.issues.push(IssueOutcome {
+            number,
+            outcome: Outcome::Skipped,
+            path: None,
+            reason: Some(reason.to_string()),
+            error_kind: None,
+            duration_ms: None,
+        });
+    }
+
+    pub fn failed(&mut self, number: u64, error: &GhError) {
+        self.issues.push(IssueOutcome {
+            number,
+            outcome: Outcome::Failed,
+            path: None,
+            reason: Some(error.to_string()),
+            error_kind: Some(error.kind()),
+            duration_ms: None,
+        });
+    }
+
+    /// Fills in the counts, status and timing. `error` is what stopped the run, if
+    /// anything did.
+    pub fn finish(&mut self, error: Option<ReportError>) {
+        let count = |outcome| self.issues.iter().filter(|i| i.outcome == outcome).count();
+        self.counts = Counts {
+            found: self.issues.len(),
+            written: count(Outcome::Written),
+            unchanged: count(Outcome::Unchanged),
+            skipped: count(Outcome::Skipped),
+            failed: count(Outcome::Failed),
+        };
+
+        self.error = error;
+        self.status = if self.error.is_some() {
+            RunStatus::Failed
+        } else if self.counts.failed > 0 {
+            RunStatus::Partial
+        } else {
+            RunStatus::Success
+        };
+
+        self.finished_at = Utc::now();
+        self.duration_ms = self.started.elapsed().as_millis() as u64;
+    }
+}
@@ -0,0 +1,342 @@
+use crate::cache::CachedIssue;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+pub struct StatsOptions {
+    pub now: DateTime<Utc>,
+    /// Only count activity at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Open issues without updates for this many days are stale
+    pub stale_days: i64,
+    /// Length of the label, reporter and commenter rankings
+    pub top: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthStats {
+    /// `YYYY-MM`
+    pub month: String,
+    pub opened: usize,
+    pub closed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Count {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StaleIssue {
+    pub number: u64,
+    pub title: String,
+    pub days_inactive: i64,
+    pub url: String,
+}
+
+/// Health metrics of a repository's issues, computed from the cache.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub generated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    /// Issues in the cache, and how many of them are open now
+    pub issues: usize,
+    pub open: usize,
+    pub months: Vec<MonthStats>,
+    /// Median hours from opening to the first comment by someone other than the
+    /// author (bots aside), over the issues that got one
+    pub median_first_response_hours: Option<f64>,
+    pub responded: usize,
+    /// Issues opened in the period that are still without a response
+    pub unanswered: usize,
+    pub median_time_to_close_hours: Option<f64>,
+    pub labels: Vec<Count>,
+    pub top_reporters: Vec<Count>,
+    pub top_commenters: Vec<Count>,
+    pub stale_days: i64,
+    pub stale: Vec<StaleIssue>,
+    /// Issues cached without their comments (csv/tsv fetches), left out of response
+    /// times and commenters
+    pub without_comments: usize,
+}
+
+pub fn compute(cached: &[CachedIssue], options: &StatsOptions) -> Stats {
+    let in_period = |date: &DateTime<Utc>| options.since.is_none_or(|since| *date >= since);
+
+    let mut months = BTreeMap::<String, (usize, usize)>::new();
+    let mut response_hours = Vec::new();
+    let mut close_hours = Vec::new();
+    let mut labels = HashMap::<&str, usize>::new();
+    let mut reporters = HashMap::<&str, usize>::new();
+    let mut commenters = HashMap::<&str, usize>::new();
+    let mut unanswered = 0;
+    let mut without_comments = 0;
+    let mut stale = Vec::new();
+
+    for CachedIssue {
+        issue, comments, ..
+    } in cached
+    {
+        let has_comments = comments.len() as u32 >= issue.comment_count();
+        if !has_comments {
+            without_comments += 1;
+        }
+
+        if in_period(issue.created_at()) {
+            months.entry(month(issue.created_at())).or_default().0 += 1;
+            for label in issue.labels() {
+                *labels.entry(label).or_default() += 1;
+            }
+            *reporters.entry(issue.user()).or_default() += 1;
+
+            if has_comments {
+                let first_response = comments
+                    .iter()
+                    .filter(|c| !c.is_bot && c.user != issue.user())
+                    .map(|c| c.created_at)
+                    .min();
+                match first_response {
+                    Some(at) => response_hours.push(hours(at - *issue.created_at())),
+                    None if issue.state() == "open" => unanswered += 1,
+                    None => {}
+                }
+            }
+        }
+
+        if let Some(closed_at) = issue.closed_at().filter(|at| in_period(at)) {
+            months.entry(month(closed_at)).or_default().1 += 1;
+            close_hours.push(hours(*closed_at - *issue.created_at()));
+        }
+
+        if has_comments {
+            for comment in comments
+                .iter()
+                .filter(|c| !c.is_bot && in_period(&c.created_at))
+            {
+                *commenters.entry(&comment.user).or_default() += 1;
+            }
+        }
+
+        let inactive = (options.now - *issue.updated_at()).num_days();
+        if issue.state() == "open" && inactive >= options.stale_days {
+            stale.push(StaleIssue {
+                number: issue.number(),
+                title: issue.title().to_string(),
+                days_inactive: inactive,
+                url: issue.html_url().to_string(),
+            });
+        }
+    }
+    stale.sort_by_key(|issue| std::cmp::Reverse(issue.days_inactive));
+
+    Stats {
+        generated_at: options.now,
+        since: options.since,
+        issues: cached.len(),
+        open: cached.iter().filter(|c| c.issue.state() == "open").count(),
+        months: months
+            .into_iter()
+            .map(|(month, (opened, closed))| MonthStats {
+                month,
+                opened,
+                closed,
+            })
+            .collect(),
+        median_first_response_hours: median(&mut response_hours),
+        responded: response_hours.len(),
+        unanswered,
+        median_time_to_close_hours: median(&mut close_hours),
+        labels: ranking(labels, options.top),
+        top_reporters: ranking(reporters, options.top),
+        top_commenters: ranking(commenters, options.top),
+        stale_days: options.stale_days,
+        stale,
+        without_comments,
+    }
+}
+
+fn month(date: &DateTime<Utc>) -> String {
+    date.format("%Y-%m").to_string()
+}
+
+fn hours(duration: Duration) -> f64 {
+    duration.num_minutes() as f64 / 60.0
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len() % 2 == 1 {
+        values[mid]
+    } else {
+        (values[mid - 1] + values[mid]) / 2.0
+    })
+}
+
+/// The `top` most frequent names, most frequent first and by name on ties.
+fn ranking(counts: HashMap<&str, usize>, top: usize) -> Vec<Count> {
+    let mut counts = counts
+        .into_iter()
+        .map(|(name, count)| Count {
+            name: name.to_string(),
+            count,
+        })
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts.truncate(top);
+    counts
+}
+
+/// Hours as the largest sensible unit, e.g. `45m`, `5.2h` or `3.1d`.
+fn format_hours(hours: Option<f64>) -> String {
+    match hours {
+        None => "n/a".to_string(),
+        Some(h) if h < 1.0 => format!("{:.0}m", h * 60.0),
+        Some(h) if h < 48.0 => format!("{:.1}h", h),
+        Some(h) => format!("{:.1}d", h / 24.0),
+    }
+}
+
+impl Stats {
+    fn summary(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Issues", self.issues.to_string()),
+            ("Open", self.open.to_string()),
+            ("Closed", (self.issues - self.open).to_string()),
+            (
+                "Median first response",
+                format!(
+                    "{} ({} responded, {} open without response)",
+                    format_hours(self.median_first_response_hours),
+                    self.responded,
+                    self.unanswered
+                ),
+            ),
+            (
+                "Median time to close",
+                format_hours(self.median_time_to_close_hours),
+            ),
+            (
+                "Stale",
+                format!(
+                    "{} open issues without updates for {}+ days",
+                    self.stale.len(),
+                    self.stale_days
+                ),
+            ),
+        ]
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if let Some(since) = self.since {
+            notes.push(format!("Activity since {}", since.format("%Y-%m-%d")));
+        }
+        if self.without_comments > 0 {
+            notes.push(format!(
+                "{} issues were cached without comments and are left out of response times and commenters",
+                self.without_comments
+            ));
+        }
+        notes
+    }
+
+    /// Plain text tables for the terminal.
+    pub fn render_table(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in self.summary() {
+            out.push_str(&format!("{:<22} {}\n", name, value));
+        }
+        for note in self.notes() {
+            out.push_str(&format!("Note: {}\n", note));
+        }
+
+        out.push_str(&format!(
+            "\n{:<8} {:>7} {:>7}\n",
+            "Month", "Opened", "Closed"
+        ));
+        for month in &self.months {
+            out.push_str(&format!(
+                "{:<8} {:>7} {:>7}\n",
+                month.month, month.opened, month.closed
+            ));
+        }
+
+        for (title, counts) in self.rankings() {
+            out.push_str(&format!("\n{}\n", title));
+            for count in counts {
+                out.push_str(&format!("  {:>5}  {}\n", count.count, count.name));
+            }
+        }
+
+        if !self.stale.is_empty() {
+            out.push_str("\nStale issues\n");
+            for issue in &self.stale {
+                out.push_str(&format!(
+                    "  #{:<6} {:>4}d  {}\n",
+                    issue.number, issue.days_inactive, issue.title
+                ));
+            }
+        }
+        out
+    }
+
+    /// A Markdown report, e.g. for a monthly health issue or wiki page.
+    pub fn render_markdown(&self) -> String {
+        let mut out = format!(
+            "# Issue Health Report\n\nGenerated {}.\n\n| Metric | Value |\n|--------|-------|\n",
+            self.generated_at.format("%Y-%m-%d")
+        );
+        for (name, value) in self.summary() {
+            out.push_str(&format!("| {} | {} |\n", name, value));
+        }
+        for note in self.notes() {
+            out.push_str(&format!("\n_{}_\n", note));
+        }
+
+        out.push_str("\n## Opened and Closed by Month\n\n| Month | Opened | Closed |\n|-------|--------|--------|\n");
+        for month in &self.months {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                month.month, month.opened, month.closed
+            ));
+        }
+
+        for (title, counts) in self.rankings() {
+            out.push_str(&format!("\n## {}\n\n", title));
+            for count in counts {
+                out.push_str(&format!("- {} ({})\n", count.name, count.count));
+            }
+        }
+
+        if !self.stale.is_empty() {
+            out.push_str(&format!(
+                "\n## Stale Issues\n\nOpen, without updates for {}+ days.\n\n",
+                self.stale_days
+            ));
+            for issue in &self.stale {
+                out.push_str(&format!(
+                    "- [#{}]({}) {} ({} days)\n",
+                    issue.number, issue.url, issue.title, issue.days_inactive
+                ));
+            }
+        }
+        out
+    }
+
+    fn rankings(&self) -> Vec<(&'static str, &[Count])> {
+        [
+            ("Labels", self.labels.as_slice()),
+            ("Top reporters", self.top_reporters.as_slice()),
+            ("Top commenters", self.top_commenters.as_slice()),
+        ]
+        .into_iter()
+        .filter(|(_, counts)| !counts.is_empty())
+        .collect()
+    }
+}